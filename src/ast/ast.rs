@@ -1,18 +1,91 @@
 use crate::token::Token;
+use crate::span::Span;
+use crate::numeric::IntKind;
+use crate::units::Unit;
 use std::fmt::{ Display, Formatter };
+use std::rc::Rc;
 
-pub enum Stmt {
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    //true for statements that end with a closing curly brace
+    pub fn ends_with_block(&self) -> bool {
+        match &self.kind {
+            StmtKind::Block(_) | StmtKind::While(..) | StmtKind::For(..) | StmtKind::Loop(..) | StmtKind::Fn(_) => true,
+            StmtKind::Expr(e) => matches!(e.kind, ExprKind::If(..)),
+            _ => false
+        }
+    }
+
+    //calls `f` on every expression of the statement, nested ones and the ones in function bodies included
+    pub fn for_each_expr(&self, f: &mut dyn FnMut(&Expr)) {
+        use StmtKind::*;
+
+        match &self.kind {
+            Expr(e) | Return(e) | Let(_, _, e) | Assign(_, e) => e.for_each_expr(f),
+            Block(body) | Loop(_, body) => body.for_each_expr(f),
+            While(_, cond, body) => {
+                cond.for_each_expr(f);
+                body.for_each_expr(f);
+            }
+            For(_, _, from, to, body) => {
+                from.for_each_expr(f);
+                to.for_each_expr(f);
+                body.for_each_expr(f);
+            }
+            Fn(decl) => decl.body.for_each_expr(f),
+            Break(_) | Continue(_) => (),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StmtKind {
     Expr(Expr),
     Block(BlockStmt),
     Let(Expr, Type, Expr),
     Assign(Expr, Expr),
     Return(Expr),
+    //loops carry an optional label that break and continue can refer to
+    While(Option<String>, Expr, BlockStmt),
+    //label, loop variable, start and end of the range (exclusive), body
+    For(Option<String>, Box<Expr>, Box<Expr>, Box<Expr>, BlockStmt),
+    Loop(Option<String>, BlockStmt),
+    Break(Option<String>),
+    Continue(Option<String>),
+    Fn(Rc<FnDecl>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub t: Type,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FnDecl {
+    pub name: String,
+    pub params: Vec<Param>,
+    pub ret: Type,
+    pub body: BlockStmt,
+    //span of the function name
+    pub span: Span,
 }
 
 #[derive(Clone, Debug)]
 pub struct Type {
     pub t: Token,
-    pub mutable: bool
+    pub mutable: bool,
+    pub span: Span,
 }
 
 impl PartialEq for Type {
@@ -29,6 +102,15 @@ impl Display for Type {
         let s = match self.t {
             IntType => "int",
             FloatType => "float",
+            BoolType => "bool",
+            StrType => "string",
+            SizedIntType(kind) => kind.name(),
+            FixedType(q) => return write!(f, "fixed<{q}>"),
+            QuantityType(ref unit) => return write!(f, "float<{unit}>"),
+            Vec3Type => "vec3",
+            QuatType => "quat",
+            PoseType => "pose",
+            Null => "null",
             ID(ref s) => s,
             _ => unreachable!()
         };
@@ -39,26 +121,87 @@ impl Display for Type {
 
 impl Type {
     pub fn new(t: Token, mutable: bool) -> Self {
-        Self { t, mutable }
+        Self { t, mutable, span: Span::default() }
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct BlockStmt {
     pub statements: Vec<Stmt>,
+    pub span: Span,
 }
 
 impl BlockStmt {
-    pub fn new() -> Self {
-        Self { statements: vec![] }
+    pub fn new(span: Span) -> Self {
+        Self { statements: vec![], span }
+    }
+
+    pub fn for_each_expr(&self, f: &mut dyn FnMut(&Expr)) {
+        for stmt in &self.statements { stmt.for_each_expr(f) }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    //calls `f` on the expression and then on the ones it is made of
+    pub fn for_each_expr(&self, f: &mut dyn FnMut(&Expr)) {
+        use ExprKind::*;
+
+        f(self);
+        match &self.kind {
+            Interp(parts) | Call(_, parts) => for part in parts { part.for_each_expr(f) },
+            Infix(lhs, _, rhs) | Index(lhs, rhs) => {
+                lhs.for_each_expr(f);
+                rhs.for_each_expr(f);
+            }
+            Prefix(_, value) | Field(value, _) | Cast(value, _) => value.for_each_expr(f),
+            If(cond, then, otherwise) => {
+                cond.for_each_expr(f);
+                then.for_each_expr(f);
+                if let Some(otherwise) = otherwise { otherwise.for_each_expr(f) }
+            }
+            _ => (),
+        }
     }
 }
 
-#[derive(Debug, Clone)]
-pub enum Expr {
-    NumLit(i64),
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    //int literal; its value may not fit in `int` until the checker has made sure it does, or converted it to the type
+    //it is used as, as in `let a: u64 = 18446744073709551615`
+    NumLit(i128),
     FloatLit(f64),
+    //integer literal of a sized type, from a suffix or an int literal used as one
+    SizedLit(i128, IntKind),
+    //raw value and number of fractional bits of a fixed-point literal, made from a number used as one
+    FixedLit(i64, u8),
+    //float literal with a unit
+    QuantityLit(f64, Unit),
+    BoolLit(bool),
+    StrLit(String),
+    //interpolated string, the values of the parts are concatenated
+    Interp(Vec<Expr>),
     Infix(Box<Expr>, Token, Box<Expr>),
     Prefix(Token, Box<Expr>),
+    //condition, then block and optional else block
+    If(Box<Expr>, BlockStmt, Option<BlockStmt>),
     Var(String),
+    //function name and arguments
+    Call(String, Vec<Expr>),
+    //indexed value and the index
+    Index(Box<Expr>, Box<Expr>),
+    //field of a vector, rotation or pose, such as p.x
+    Field(Box<Expr>, String),
+    //value converted to another type with `as`
+    Cast(Box<Expr>, Type),
     Noop,
 }
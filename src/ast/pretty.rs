@@ -0,0 +1,125 @@
+use crate::ast::ast::{BlockStmt, Expr, ExprKind, Stmt, StmtKind, Type};
+use crate::numeric::fixed_to_f64;
+
+//renders the tree one node per line, children indented under their parent
+pub fn pretty_print(blk: &BlockStmt) -> String {
+    let mut printer = Printer { out: String::new(), depth: 0 };
+    printer.block("Block", blk);
+    printer.out
+}
+
+//one line describing the statement, without its children
+pub fn describe_stmt(stmt: &Stmt) -> String {
+    let mut printer = Printer { out: String::new(), depth: 0 };
+    printer.stmt(stmt);
+    printer.out.lines().next().unwrap_or("").to_string()
+}
+
+struct Printer {
+    out: String,
+    depth: usize,
+}
+
+fn type_str(t: &Type) -> String {
+    if t.mutable { format!("mut {t}") } else { t.to_string() }
+}
+
+fn label_str(label: &Option<String>) -> String {
+    match label {
+        Some(l) => format!(" '{l}"),
+        None => "".to_string(),
+    }
+}
+
+impl Printer {
+    fn line(&mut self, text: &str) {
+        self.out.push_str(&"  ".repeat(self.depth));
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    //prints `text` with the nodes added by `children` under it
+    fn node(&mut self, text: &str, children: impl FnOnce(&mut Self)) {
+        self.line(text);
+        self.depth += 1;
+        children(self);
+        self.depth -= 1;
+    }
+
+    fn block(&mut self, text: &str, blk: &BlockStmt) {
+        self.node(text, |p| for stmt in &blk.statements { p.stmt(stmt) });
+    }
+
+    fn var_name(e: &Expr) -> &str {
+        match &e.kind {
+            ExprKind::Var(name) => name,
+            _ => "?",
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        use StmtKind::*;
+
+        match &stmt.kind {
+            Expr(e) => self.expr(e),
+            Block(blk) => self.block("Block", blk),
+            Let(var, t, value) => self.node(&format!("Let {}: {}", Self::var_name(var), type_str(t)), |p| {
+                if value.kind != ExprKind::Noop { p.expr(value) }
+            }),
+            Assign(var, value) => self.node(&format!("Assign {}", Self::var_name(var)), |p| p.expr(value)),
+            Return(value) => self.node("Return", |p| {
+                if value.kind != ExprKind::Noop { p.expr(value) }
+            }),
+            While(label, cond, body) => self.node(&format!("While{}", label_str(label)), |p| {
+                p.expr(cond);
+                p.block("Block", body);
+            }),
+            For(label, var, from, to, body) => self.node(&format!("For{} {}", label_str(label), Self::var_name(var)), |p| {
+                p.expr(from);
+                p.expr(to);
+                p.block("Block", body);
+            }),
+            Loop(label, body) => self.block(&format!("Loop{}", label_str(label)), body),
+            Break(label) => self.line(&format!("Break{}", label_str(label))),
+            Continue(label) => self.line(&format!("Continue{}", label_str(label))),
+            Fn(decl) => {
+                let params: Vec<String> = decl.params.iter().map(|p| format!("{}: {}", p.name, type_str(&p.t))).collect();
+                self.block(&format!("Fn {}({}) -> {}", decl.name, params.join(", "), decl.ret), &decl.body);
+            }
+        }
+    }
+
+    fn expr(&mut self, e: &Expr) {
+        use ExprKind::*;
+
+        match &e.kind {
+            NumLit(n) => self.line(&format!("Int {n}")),
+            FloatLit(f) => self.line(&format!("Float {f:?}")),
+            QuantityLit(f, unit) => self.line(&format!("Float {f:?} (float<{unit}>)")),
+            SizedLit(n, kind) => self.line(&format!("Int {n}{}", kind.name())),
+            FixedLit(raw, q) => self.line(&format!("Fixed {} (fixed<{q}>)", fixed_to_f64(*raw, *q))),
+            BoolLit(b) => self.line(&format!("Bool {b}")),
+            StrLit(s) => self.line(&format!("Str {s:?}")),
+            Interp(parts) => self.node("Interp", |p| for part in parts { p.expr(part) }),
+            Infix(lhs, op, rhs) => self.node(&format!("Infix {op}"), |p| {
+                p.expr(lhs);
+                p.expr(rhs);
+            }),
+            Prefix(op, rhs) => self.node(&format!("Prefix {op}"), |p| p.expr(rhs)),
+            Field(value, name) => self.node(&format!("Field {name}"), |p| p.expr(value)),
+            If(cond, then, otherwise) => self.node("If", |p| {
+                p.expr(cond);
+                p.block("Then", then);
+                if let Some(otherwise) = otherwise { p.block("Else", otherwise) }
+            }),
+            Var(name) => self.line(&format!("Var {name}")),
+            Call(name, args) => self.node(&format!("Call {name}"), |p| for arg in args { p.expr(arg) }),
+            Index(value, index) => self.node("Index", |p| {
+                p.expr(value);
+                p.expr(index);
+            }),
+            Cast(value, t) => self.node(&format!("Cast {t}"), |p| p.expr(value)),
+            Noop => self.line("Noop"),
+        }
+    }
+}
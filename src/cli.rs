@@ -0,0 +1,251 @@
+use std::io::Read;
+
+use arm_shell::ast::pretty::pretty_print;
+use arm_shell::engine::{Engine, Error, ErrorKind};
+
+use crate::repl;
+
+pub const EXIT_SUCCESS: i32 = 0;
+pub const EXIT_RUNTIME: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_PARSE: i32 = 3;
+pub const EXIT_TYPE: i32 = 4;
+pub const EXIT_IO: i32 = 5;
+
+const USAGE: &str = "\
+Usage: arm-shell [OPTIONS] [COMMAND]
+       arm-shell [OPTIONS] <file> [-- <args>...]
+
+Commands:
+  run <file> [-- <args>...]  run a script; `-` reads it from stdin
+  check <file>               parse and type check a script without running it
+  repl                       start the interactive shell (the default)
+
+Options:
+  -e <code>        run the code given on the command line
+  --dump-tokens    print the tokens of the script instead of running it
+  --dump-ast       print the syntax tree of the script instead of running it
+  --trace          print every statement as it is run
+  -V, --version    print the version
+  -h, --help       print this help
+
+The arguments after `--` are available to the script as the strings `arg1`, `arg2`, ...
+and their number as `argc`; these names are only defined for a script given arguments.
+
+Diagnostics are printed to stderr, the value of the script to stdout.
+
+Exit codes: 0 success, 1 runtime error, 2 usage error, 3 parse error, 4 type error,
+5 script not readable";
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Run(String),
+    Check(String),
+    Eval(String),
+    Repl,
+    Help,
+    Version,
+}
+
+#[derive(Debug)]
+struct Options {
+    command: Command,
+    script_args: Vec<String>,
+    dump_tokens: bool,
+    dump_ast: bool,
+    trace: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut opts = Options { command: Command::Repl, script_args: vec![], dump_tokens: false, dump_ast: false, trace: false };
+    let mut positional: Vec<String> = vec![];
+    let mut check = false;
+    let mut code = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--" => { opts.script_args = iter.by_ref().cloned().collect(); }
+            "-h" | "--help" => return Ok(Options { command: Command::Help, ..opts }),
+            "-V" | "--version" => return Ok(Options { command: Command::Version, ..opts }),
+            "-e" => match iter.next() {
+                Some(c) => code = Some(c.clone()),
+                None => return Err("`-e` needs the code to run".to_string()),
+            },
+            "--dump-tokens" => opts.dump_tokens = true,
+            "--dump-ast" => opts.dump_ast = true,
+            "--trace" => opts.trace = true,
+            "--check" => check = true,
+            //a lone `-` is the stdin script
+            a if a.starts_with('-') && a != "-" => return Err(format!("unknown option `{a}`")),
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    opts.command = match (positional.next().as_deref(), positional.next()) {
+        (Some("run"), Some(file)) => Command::Run(file),
+        (Some("check"), Some(file)) => Command::Check(file),
+        (Some(cmd @ ("run" | "check")), None) => return Err(format!("`{cmd}` needs a file")),
+        (Some("repl"), None) => Command::Repl,
+        (Some(file), None) => Command::Run(file.to_string()),
+        (None, None) => Command::Repl,
+        (_, Some(extra)) => return Err(format!("unexpected argument `{extra}`")),
+    };
+    if let Some(extra) = positional.next() {
+        return Err(format!("unexpected argument `{extra}`"))
+    }
+
+    if let Some(code) = code {
+        if opts.command != Command::Repl { return Err("`-e` can't be used with a script file".to_string()) }
+        opts.command = Command::Eval(code);
+    }
+
+    if !opts.script_args.is_empty() && !matches!(opts.command, Command::Run(_) | Command::Check(_)) {
+        return Err("the arguments after `--` need a script".to_string())
+    }
+
+    if check {
+        opts.command = match opts.command {
+            Command::Run(file) => Command::Check(file),
+            Command::Check(file) => Command::Check(file),
+            _ => return Err("`--check` needs a file".to_string()),
+        };
+    }
+
+    Ok(opts)
+}
+
+//exit code telling apart code that didn't parse, didn't type check or failed while running
+fn exit_code(e: &Error) -> i32 {
+    match e.kind() {
+        ErrorKind::Io => EXIT_IO,
+        ErrorKind::Parse => EXIT_PARSE,
+        ErrorKind::Type => EXIT_TYPE,
+        ErrorKind::Runtime => EXIT_RUNTIME,
+    }
+}
+
+//reads the script, `-` being stdin; returns the name to report it under and its text
+fn read_script(path: &str) -> Result<(String, String), String> {
+    if path == "-" {
+        let mut text = String::new();
+        return match std::io::stdin().read_to_string(&mut text) {
+            Ok(_) => Ok(("<stdin>".to_string(), text)),
+            Err(e) => Err(format!("cannot read the script from stdin: {e}")),
+        }
+    }
+
+    match std::fs::read_to_string(path) {
+        Ok(text) => Ok((path.to_string(), text)),
+        Err(e) => Err(format!("cannot read `{path}`: {e}")),
+    }
+}
+
+//parses the command line and does what it asks, with calls nested up to `max_call_depth` deep; returns the exit code
+pub fn main(args: &[String], max_call_depth: usize) -> i32 {
+    let opts = match parse_args(args) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("error: {e}\n\nFor more information, try `--help`.");
+            return EXIT_USAGE;
+        }
+    };
+
+    let mut engine = Engine::new();
+    engine.set_trace(opts.trace);
+    engine.set_max_call_depth(max_call_depth);
+    //only a script given arguments has them as globals, the other code is free to use the names
+    if !opts.script_args.is_empty() {
+        //new globals of a storable type are always accepted
+        let _ = engine.set_global("argc", opts.script_args.len() as i64);
+        for (i, arg) in opts.script_args.iter().enumerate() {
+            let _ = engine.set_global(&format!("arg{}", i + 1), arg.as_str());
+        }
+    }
+
+    let (file, text) = match &opts.command {
+        Command::Help => { println!("{USAGE}"); return EXIT_SUCCESS }
+        Command::Version => { println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")); return EXIT_SUCCESS }
+        Command::Repl => { repl::run(engine.interpreter_mut()); return EXIT_SUCCESS }
+        Command::Eval(code) => ("<command line>".to_string(), code.clone()),
+        Command::Run(path) | Command::Check(path) => match read_script(path) {
+            Ok(script) => script,
+            Err(e) => { eprintln!("error: {e}"); return EXIT_IO }
+        },
+    };
+
+    if opts.dump_tokens {
+        return if repl::print_tokens(text, &file) { EXIT_SUCCESS } else { EXIT_PARSE }
+    }
+
+    if opts.dump_ast {
+        let inter = engine.interpreter_mut();
+        inter.set_file(&file);
+        inter.feed_next_line(text, &mut false);
+        return match inter.parse() {
+            Ok(tree) => { print!("{}", pretty_print(&tree)); EXIT_SUCCESS }
+            Err(e) => { e.emit(inter.sources()); EXIT_PARSE }
+        }
+    }
+
+    //the value is printed as the repl shows it, with its unit
+    let res = match opts.command {
+        Command::Check(_) => engine.check(&file, text).map(|_| None),
+        _ => engine.run_to_string(&file, text),
+    };
+
+    match res {
+        Ok(None) => EXIT_SUCCESS,
+        Ok(Some(res)) => { println!("{}", res); EXIT_SUCCESS }
+        Err(e) => { e.emit(); exit_code(&e) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Options, String> {
+        let args: Vec<String> = args.split_whitespace().map(str::to_string).collect();
+        parse_args(&args)
+    }
+
+    fn command(args: &str) -> Command {
+        match parse(args) {
+            Ok(opts) => opts.command,
+            Err(e) => panic!("{args}: {e}"),
+        }
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(command(""), Command::Repl);
+        assert_eq!(command("repl"), Command::Repl);
+        assert_eq!(command("run a.robot"), Command::Run("a.robot".to_string()));
+        assert_eq!(command("a.robot"), Command::Run("a.robot".to_string()));
+        assert_eq!(command("-"), Command::Run("-".to_string()));
+        assert_eq!(command("check a.robot"), Command::Check("a.robot".to_string()));
+        assert_eq!(command("--check a.robot"), Command::Check("a.robot".to_string()));
+        assert_eq!(command("-e 1+2"), Command::Eval("1+2".to_string()));
+        assert_eq!(command("run a.robot --help"), Command::Help);
+        assert_eq!(command("-V"), Command::Version);
+    }
+
+    #[test]
+    fn options_and_script_arguments() {
+        let opts = parse("--trace --dump-ast run a.robot -- x --trace").unwrap();
+        assert!(opts.trace && opts.dump_ast && !opts.dump_tokens);
+        //what follows `--` belongs to the script, options included
+        assert_eq!(opts.script_args, ["x", "--trace"]);
+        assert_eq!(parse("a.robot --").unwrap().script_args, Vec::<String>::new());
+    }
+
+    #[test]
+    fn usage_errors() {
+        for args in ["run", "check", "-e", "--color", "a.robot b.robot", "run a.robot b.robot", "-e 1 a.robot",
+            "--check", "--check -e 1", "-- x", "repl -- x", "-e 1 -- x"] {
+            assert!(parse(args).is_err(), "{args}");
+        }
+    }
+}
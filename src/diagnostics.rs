@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::fmt::{ Display, Formatter };
+use std::io::IsTerminal;
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::span::{Span, TAB_WIDTH};
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn colour(&self) -> &'static str {
+        match self {
+            Self::Error => RED,
+            Self::Warning => YELLOW,
+        }
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+//a message about a location in the source, rendered rustc-style with the offending line underlined
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Option<Span>,
+    pub label: Option<String>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+//the text of the files diagnostics can point into, by file name
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    files: HashMap<String, String>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //sets the text of the file, replacing the one it had
+    pub fn add(&mut self, file: &str, text: &str) {
+        self.files.insert(file.to_string(), text.to_string());
+    }
+
+    pub fn get(&self, file: &str) -> Option<&str> {
+        self.files.get(file).map(String::as_str)
+    }
+
+    //the files the diagnostics point into, so that they can be rendered later
+    pub fn subset(&self, diagnostics: &[Diagnostic]) -> SourceMap {
+        let mut res = SourceMap::new();
+        for span in diagnostics.iter().filter_map(|d| d.span.as_ref()) {
+            if let Some(text) = self.get(&span.file) { res.add(&span.file, text) }
+        }
+        res
+    }
+}
+
+pub trait ToDiagnostic {
+    fn to_diagnostic(&self) -> Diagnostic;
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            span: None,
+            label: None,
+            notes: vec![],
+            help: None,
+        }
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self { severity: Severity::Warning, ..Self::error(message) }
+    }
+
+    pub fn with_span(mut self, span: &Span) -> Self {
+        self.span = Some(span.clone());
+        self
+    }
+
+    //text printed next to the caret
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    //renders the diagnostic, showing the line the span points into from its file in `sources`
+    pub fn render(&self, sources: &SourceMap, colour: bool) -> String {
+        let paint = |c: &'static str| if colour { c } else { "" };
+        let (sev, blue, bold, reset) = (paint(self.severity.colour()), paint(BLUE), paint(BOLD), paint(RESET));
+
+        let mut out = format!("{sev}{}{reset}{bold}: {}{reset}\n", self.severity, self.message);
+
+        let gutter = match &self.span {
+            Some(span) => span.line.to_string().len(),
+            None => 1,
+        };
+        let pad = " ".repeat(gutter);
+
+        if let Some(span) = &self.span {
+            out.push_str(&format!("{pad}{blue}-->{reset} {span}\n"));
+
+            //split rather than lines so a span at the very end of the source, after a trailing newline, still has a line.
+            //No line is shown for a file whose text is not known
+            if let Some(line) = sources.get(&span.file).and_then(|text| text.split('\n').nth(span.line - 1)) {
+                let line = line.strip_suffix('\r').unwrap_or(line);
+                //tabs are expanded so the underline lines up with the display columns of the span
+                let line = line.replace('\t', &" ".repeat(TAB_WIDTH));
+                let offset = " ".repeat(span.col - 1);
+                let max_len = line.width().saturating_sub(span.col - 1).max(1);
+                let underline = "^".repeat(span.len.clamp(1, max_len));
+                let label = match &self.label {
+                    Some(l) => format!(" {l}"),
+                    None => "".to_string(),
+                };
+
+                out.push_str(&format!("{pad} {blue}|{reset}\n"));
+                out.push_str(&format!("{blue}{}{reset} {blue}|{reset} {line}\n", span.line));
+                out.push_str(&format!("{pad} {blue}|{reset} {offset}{sev}{underline}{label}{reset}\n"));
+            }
+        }
+
+        if !self.notes.is_empty() || self.help.is_some() {
+            out.push_str(&format!("{pad} {blue}|{reset}\n"));
+        }
+
+        for note in &self.notes {
+            out.push_str(&format!("{pad} {blue}={reset} {bold}note{reset}: {note}\n"));
+        }
+
+        if let Some(help) = &self.help {
+            out.push_str(&format!("{pad} {blue}={reset} {bold}help{reset}: {help}\n"));
+        }
+
+        out
+    }
+
+    //prints the diagnostic to stderr, coloured if stderr is a terminal
+    pub fn emit(&self, sources: &SourceMap) {
+        eprint!("{}", self.render(sources, std::io::stderr().is_terminal()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources(text: &str) -> SourceMap {
+        let mut sources = SourceMap::new();
+        sources.add("a.arm", text);
+        sources
+    }
+
+    fn span(line: usize, col: usize, len: usize) -> Span {
+        Span::new("a.arm".into(), line, col, len)
+    }
+
+    #[test]
+    fn renders_the_line_under_the_message() {
+        let diag = Diagnostic::error("Mismatched types")
+            .with_span(&span(2, 14, 4))
+            .with_label("expected `int`")
+            .with_note("the variable is declared here")
+            .with_help("add `as int`");
+        assert_eq!(diag.render(&sources("let a: int = 1;\nlet b: int = true;"), false), "\
+error: Mismatched types
+ --> a.arm:2:14
+  |
+2 | let b: int = true;
+  |              ^^^^ expected `int`
+  |
+  = note: the variable is declared here
+  = help: add `as int`
+");
+    }
+
+    #[test]
+    fn gutter_fits_the_line_number() {
+        let text = "\n".repeat(9) + "x";
+        let diag = Diagnostic::warning("Unused").with_span(&span(10, 1, 1));
+        assert_eq!(diag.render(&sources(&text), false), "\
+warning: Unused
+  --> a.arm:10:1
+   |
+10 | x
+   | ^
+");
+    }
+
+    #[test]
+    fn underline_follows_tabs_and_stays_on_the_line() {
+        let diag = Diagnostic::error("Bad").with_span(&span(1, 6, 20));
+        assert_eq!(diag.render(&sources("\tab cd"), false), "\
+error: Bad
+ --> a.arm:1:6
+  |
+1 |     ab cd
+  |      ^^^^
+");
+        //the end of the input after a trailing newline is on an empty line
+        let diag = Diagnostic::error("Unexpected end of input").with_span(&span(2, 1, 1));
+        assert!(diag.render(&sources("f(\n"), false).ends_with("2 | \n  | ^\n"));
+    }
+
+    #[test]
+    fn no_snippet_without_a_span_or_its_file() {
+        assert_eq!(Diagnostic::error("Aborting").render(&SourceMap::new(), false), "error: Aborting\n");
+        let diag = Diagnostic::error("Bad").with_span(&span(1, 1, 1));
+        assert_eq!(diag.render(&SourceMap::new(), false), "error: Bad\n --> a.arm:1:1\n");
+    }
+
+    #[test]
+    fn colours_only_when_asked() {
+        let diag = Diagnostic::error("Bad");
+        assert_eq!(diag.render(&SourceMap::new(), true), format!("{RED}error{RESET}{BOLD}: Bad{RESET}\n"));
+    }
+}
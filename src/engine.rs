@@ -0,0 +1,489 @@
+use std::fmt::{ Display, Formatter };
+use std::path::Path;
+
+use crate::diagnostics::{Diagnostic, SourceMap};
+use crate::interpreter::{Interpreter, InterpreterError};
+use crate::lexer::Lexer;
+use crate::object::{NativeFn, Object};
+use crate::numeric::{fixed_from_f64, fixed_from_int, fixed_to_f64};
+use crate::parser::Parser;
+use crate::geometry::{Pose, Quat, Vec3};
+use crate::token::Token;
+
+//a value exchanged between Rust and scripts; sized integers come out as Int, or as Float for the u64 values too large
+//for it, and fixed-point numbers and floats with a unit as Float, in that unit
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    Vec3(Vec3),
+    Quat(Quat),
+    Pose(Pose),
+    //a script function, described by its signature; functions can't be passed back in
+    Fn(String),
+}
+
+impl Value {
+    fn from_object(obj: Object) -> Self {
+        match obj {
+            Object::Int(i) => Value::Int(i),
+            Object::SizedInt(i, _) => i64::try_from(i).map_or(Value::Float(i as f64), Value::Int),
+            Object::Float(f) => Value::Float(f),
+            Object::Fixed(raw, q) => Value::Float(fixed_to_f64(raw, q)),
+            Object::Quantity(f, _) => Value::Float(f),
+            Object::Bool(b) => Value::Bool(b),
+            Object::Str(s) => Value::Str(s),
+            Object::Vec3(v) => Value::Vec3(v),
+            Object::Quat(q) => Value::Quat(q),
+            Object::Pose(p) => Value::Pose(p),
+            Object::Fn(_) | Object::Native(_) => Value::Fn(obj.to_string()),
+            Object::Return(obj) => Value::from_object(*obj),
+            Object::Break(_) | Object::Continue(_) | Object::Null => Value::Null,
+        }
+    }
+
+    fn to_object(&self) -> Option<Object> {
+        match self {
+            Value::Int(i) => Some(Object::Int(*i)),
+            Value::Float(f) => Some(Object::Float(*f)),
+            Value::Bool(b) => Some(Object::Bool(*b)),
+            Value::Str(s) => Some(Object::Str(s.clone())),
+            Value::Vec3(v) => Some(Object::Vec3(*v)),
+            Value::Quat(q) => Some(Object::Quat(*q)),
+            Value::Pose(p) => Some(Object::Pose(*p)),
+            Value::Null | Value::Fn(_) => None,
+        }
+    }
+}
+
+//type of the parameters and results of the functions registered by the host
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueType {
+    Null,
+    Int,
+    Float,
+    Bool,
+    Str,
+    Vec3,
+    Quat,
+    Pose,
+}
+
+impl ValueType {
+    fn token(&self) -> Token {
+        match self {
+            ValueType::Null => Token::Null,
+            ValueType::Int => Token::IntType,
+            ValueType::Float => Token::FloatType,
+            ValueType::Bool => Token::BoolType,
+            ValueType::Str => Token::StrType,
+            ValueType::Vec3 => Token::Vec3Type,
+            ValueType::Quat => Token::QuatType,
+            ValueType::Pose => Token::PoseType,
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(i: i64) -> Self { Value::Int(i) }
+}
+
+impl From<f64> for Value {
+    fn from(f: f64) -> Self { Value::Float(f) }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self { Value::Bool(b) }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self { Value::Str(s.to_string()) }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self { Value::Str(s) }
+}
+
+impl From<Vec3> for Value {
+    fn from(v: Vec3) -> Self { Value::Vec3(v) }
+}
+
+impl From<Quat> for Value {
+    fn from(q: Quat) -> Self { Value::Quat(q) }
+}
+
+impl From<Pose> for Value {
+    fn from(p: Pose) -> Self { Value::Pose(p) }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            Value::Null => write!(f, "Null"),
+            Value::Int(i) => write!(f, "{i}"),
+            Value::Float(fl) => write!(f, "{fl:?}"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Str(s) | Value::Fn(s) => write!(f, "{s}"),
+            Value::Vec3(v) => write!(f, "{v}"),
+            Value::Quat(q) => write!(f, "{q}"),
+            Value::Pose(p) => write!(f, "{p}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    //the script could not be read
+    Io,
+    Parse,
+    Type,
+    Runtime,
+}
+
+//everything that went wrong in one call, with the text of the files the diagnostics point into
+#[derive(Clone, Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    diagnostics: Vec<Diagnostic>,
+    sources: SourceMap,
+}
+
+impl Error {
+    fn new(kind: ErrorKind, diagnostic: Diagnostic) -> Self {
+        Self { kind, diagnostics: vec![diagnostic], sources: SourceMap::new() }
+    }
+
+    pub fn from_interpreter(e: &InterpreterError, sources: &SourceMap) -> Self {
+        let diagnostics = e.diagnostics();
+        Self { kind: Self::kind_of(e), sources: sources.subset(&diagnostics), diagnostics }
+    }
+
+    fn kind_of(e: &InterpreterError) -> ErrorKind {
+        match e {
+            InterpreterError::ParseError(_) => ErrorKind::Parse,
+            InterpreterError::SymbolError(_) => ErrorKind::Type,
+            //code that neither parses nor type checks counts as a parse failure
+            InterpreterError::CheckFailed(errors) => match errors.iter().any(|e| Self::kind_of(e) == ErrorKind::Parse) {
+                true => ErrorKind::Parse,
+                false => ErrorKind::Type,
+            },
+            _ => ErrorKind::Runtime,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn render(&self, colour: bool) -> String {
+        self.diagnostics.iter().map(|d| d.render(&self.sources, colour)).collect()
+    }
+
+    //prints the diagnostics to stderr, coloured if stderr is a terminal
+    pub fn emit(&self) {
+        for diag in &self.diagnostics {
+            diag.emit(&self.sources);
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.render(false).trim_end())
+    }
+}
+
+impl std::error::Error for Error {}
+
+//an interpreter session; globals and functions defined by one call stay visible to the next ones
+pub struct Engine {
+    inter: Interpreter,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self { inter: Interpreter::new(Parser::new(Lexer::new())) }
+    }
+
+    //runs the code and returns the value of its last statement
+    pub fn eval(&mut self, code: &str) -> Result<Value, Error> {
+        self.run("<eval>", code.to_string())
+    }
+
+    pub fn run_file(&mut self, path: impl AsRef<Path>) -> Result<Value, Error> {
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(text) => self.run(&path.display().to_string(), text),
+            Err(e) => Err(Error::new(ErrorKind::Io, Diagnostic::error(format!("Cannot read `{}`: {e}", path.display())))),
+        }
+    }
+
+    //runs the text, reporting errors as coming from `file`
+    pub fn run(&mut self, file: &str, text: String) -> Result<Value, Error> {
+        self.interpret(file, text).map(Value::from_object)
+    }
+
+    //like run, but returns the value as the scripts show it, with its unit or all the digits of a u64, or None if the
+    //code has no value
+    pub fn run_to_string(&mut self, file: &str, text: String) -> Result<Option<String>, Error> {
+        let obj = match self.interpret(file, text)? {
+            Object::Return(obj) => *obj,
+            obj => obj,
+        };
+        match obj {
+            Object::Null | Object::Break(_) | Object::Continue(_) => Ok(None),
+            obj => Ok(Some(obj.to_string())),
+        }
+    }
+
+    fn interpret(&mut self, file: &str, text: String) -> Result<Object, Error> {
+        self.feed(file, text);
+        self.inter.interpret().map_err(|e| Error::from_interpreter(&e, self.inter.sources()))
+    }
+
+    //parses and type checks the text without running it
+    pub fn check(&mut self, file: &str, text: String) -> Result<(), Error> {
+        self.feed(file, text);
+        match self.inter.check() {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::from_interpreter(&e, self.inter.sources())),
+        }
+    }
+
+    fn feed(&mut self, file: &str, text: String) {
+        self.inter.set_file(file);
+        self.inter.feed_next_line(text, &mut false);
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.inter.get_global(name).cloned().map(Value::from_object)
+    }
+
+    //sets a global variable: an existing one must keep its type, a new one is declared immutable for the scripts
+    pub fn set_global(&mut self, name: &str, value: impl Into<Value>) -> Result<(), Error> {
+        let value = value.into();
+        let Some(obj) = value.to_object() else {
+            return Err(Error::new(ErrorKind::Type, Diagnostic::error(format!("Cannot store `{value}` in a variable"))))
+        };
+
+        if let Some(Object::Fn(_) | Object::Native(_)) = self.inter.get_global(name) {
+            return Err(Error::new(ErrorKind::Type, Diagnostic::error(format!("`{name}` is a function"))))
+        }
+
+        match self.inter.global_type(name) {
+            Some(t) => match Self::convert(&obj, &t.t) {
+                Some(obj) => { self.inter.set_global(name, obj); Ok(()) }
+                None => Err(Error::new(ErrorKind::Type,
+                    Diagnostic::error(format!("Global `{name}` has type `{t}`, found `{}`", obj.type_name()))
+                )),
+            },
+            None => { self.inter.define_global(name, obj); Ok(()) }
+        }
+    }
+
+    //value for a global of type `t`: ints are converted to sized integers if they fit, ints and floats to fixed-point
+    //numbers saturating at the bounds of the type, and to floats in the unit of the type
+    fn convert(obj: &Object, t: &Token) -> Option<Object> {
+        match (obj, t) {
+            (obj, t) if obj.type_token().as_ref() == Some(t) => Some(obj.clone()),
+            (Object::Int(i), Token::SizedIntType(kind)) => kind.contains(*i as i128).then_some(Object::SizedInt(*i as i128, *kind)),
+            (Object::Int(i), Token::FixedType(q)) => Some(Object::Fixed(fixed_from_int(*i as i128, *q), *q)),
+            (Object::Float(f), Token::FixedType(q)) => Some(Object::Fixed(fixed_from_f64(*f, *q), *q)),
+            (Object::Int(i), Token::QuantityType(unit)) => Some(Object::Quantity(*i as f64, unit.clone())),
+            (Object::Float(f), Token::QuantityType(unit)) => Some(Object::Quantity(*f, unit.clone())),
+            _ => None,
+        }
+    }
+
+    //makes `func` callable from scripts as `name`; the scripts are type checked against `params` and `ret`,
+    //an error returned by `func` stops the script with a runtime error pointing at the call
+    pub fn register_fn(&mut self, name: &str, params: &[ValueType], ret: ValueType,
+                       func: impl Fn(&[Value]) -> Result<Value, String> + 'static) {
+        self.inter.register_fn(Self::native(name, params, ret, func));
+    }
+
+    //like register_fn, for functions taking any number of arguments of type `rest` after `params`
+    pub fn register_variadic_fn(&mut self, name: &str, params: &[ValueType], rest: ValueType, ret: ValueType,
+                                func: impl Fn(&[Value]) -> Result<Value, String> + 'static) {
+        self.inter.register_fn(Self::native(name, params, ret, func).variadic(rest.token()));
+    }
+
+    fn native(name: &str, params: &[ValueType], ret: ValueType, func: impl Fn(&[Value]) -> Result<Value, String> + 'static) -> NativeFn {
+        let params: Vec<Token> = params.iter().map(ValueType::token).collect();
+        NativeFn::new(name, &params, ret.token(), move |args| {
+            let args: Vec<Value> = args.iter().cloned().map(Value::from_object).collect();
+            let res = func(&args)?;
+            Ok(res.to_object().unwrap_or(Object::Null))
+        })
+    }
+
+    //all the global variables and functions except the predefined ones, sorted by name
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        self.inter.vars().into_iter().map(|(name, obj)| (name, Value::from_object(obj))).collect()
+    }
+
+    //prints every statement to stderr before running it
+    pub fn set_trace(&mut self, trace: bool) {
+        self.inter.set_trace(trace);
+    }
+
+    //limits the nesting of script function calls, DEFAULT_MAX_CALL_DEPTH unless set; a host running the engine on a
+    //thread with a larger stack can allow deeper recursion
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.inter.set_max_call_depth(depth);
+    }
+
+    //forgets every global and function
+    pub fn reset(&mut self) {
+        self.inter.reset();
+    }
+
+    //the interpreter behind the engine, for tools that need more than running code
+    pub fn interpreter_mut(&mut self) -> &mut Interpreter {
+        &mut self.inter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::DEFAULT_MAX_CALL_DEPTH;
+
+    const COUNT_DOWN: &str = "fn down(n: int) -> int { if n == 0 { 0 } else { down(n - 1) + 1 } }";
+
+    fn stack_overflow(engine: &mut Engine, code: &str) -> String {
+        let e = engine.eval(code).expect_err("the recursion should have been stopped");
+        assert_eq!(e.kind(), ErrorKind::Runtime);
+        e.to_string()
+    }
+
+    #[test]
+    fn deep_recursion_is_an_error() {
+        //the default depth takes about 7MB of stack in debug builds
+        let thread = std::thread::Builder::new().stack_size(16 * 1024 * 1024).spawn(|| {
+            let mut engine = Engine::new();
+            engine.eval(COUNT_DOWN).unwrap();
+            assert_eq!(engine.eval("down(150)").unwrap(), Value::Int(150));
+            let message = stack_overflow(&mut engine, "down(100000)");
+            assert!(message.contains(&format!("more than {DEFAULT_MAX_CALL_DEPTH} nested calls")), "{message}");
+            //the engine can still be used
+            assert_eq!(engine.eval("down(3)").unwrap(), Value::Int(3));
+        });
+        thread.unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn max_call_depth_can_be_set() {
+        let mut engine = Engine::new();
+        engine.set_max_call_depth(20);
+        engine.eval(COUNT_DOWN).unwrap();
+        assert_eq!(engine.eval("down(19)").unwrap(), Value::Int(19));
+        let message = stack_overflow(&mut engine, "down(20)");
+        assert!(message.contains("more than 20 nested calls"), "{message}");
+    }
+
+    #[test]
+    fn errors_show_the_file_they_point_into() {
+        let mut engine = Engine::new();
+        engine.run("lib.arm", "fn half(x: int) -> int {\n    x / 0\n}".to_string()).unwrap();
+        let message = engine.eval("let a: int = 1;\nhalf(a)").unwrap_err().to_string();
+        assert!(message.contains("--> lib.arm:2:5"), "{message}");
+        assert!(message.contains("2 |     x / 0"), "{message}");
+    }
+
+    #[test]
+    fn errors_show_the_input_they_point_into() {
+        let mut engine = Engine::new();
+        engine.eval("fn f(x: int) -> int {\n    x / 0\n}").unwrap();
+        engine.eval("let b: int = 2").unwrap();
+        let message = engine.eval("let a: int = 5; f(a)").unwrap_err().to_string();
+        assert!(message.contains("--> <eval>:2:5"), "{message}");
+        assert!(message.contains("2 |     x / 0"), "{message}");
+
+        //errors in the latest input point into it
+        let message = engine.eval("f(b) + 1 / 0").unwrap_err().to_string();
+        assert!(message.contains("--> <eval>:2:5"), "{message}");
+        let message = engine.eval("b / 0").unwrap_err().to_string();
+        assert!(message.contains("--> <eval>#5:1:1"), "{message}");
+        assert!(message.contains("1 | b / 0"), "{message}");
+    }
+
+    #[test]
+    fn values_are_shown_as_in_scripts() {
+        let mut engine = Engine::new();
+        let mut show = |code: &str| engine.run_to_string("<test>", code.to_string()).unwrap();
+        assert_eq!(show("10mm + 1cm").as_deref(), Some("20mm"));
+        assert_eq!(show("18446744073709551615u64").as_deref(), Some("18446744073709551615"));
+        assert_eq!(show("let a: int = 1"), None);
+        assert_eq!(show("-128i8").as_deref(), Some("-128"));
+        assert_eq!(show("let b: u64 = 18446744073709551615;\nb").as_deref(), Some("18446744073709551615"));
+    }
+
+    #[test]
+    fn globals_keep_their_type_and_functions_their_name() {
+        let mut engine = Engine::new();
+        engine.set_global("speed", 2.5).unwrap();
+        engine.eval("let steps: u8 = 1; let gap: float<mm> = 1mm; fn f() -> int { 1 }").unwrap();
+        engine.set_global("steps", 200).unwrap();
+        engine.set_global("gap", 3).unwrap();
+        assert_eq!(engine.run_to_string("<test>", "speed * 2.0".to_string()).unwrap().as_deref(), Some("5.0"));
+        assert_eq!(engine.run_to_string("<test>", "steps".to_string()).unwrap().as_deref(), Some("200"));
+        assert_eq!(engine.run_to_string("<test>", "gap".to_string()).unwrap().as_deref(), Some("3mm"));
+
+        for (name, value) in [("steps", Value::from(256)), ("speed", Value::from("fast")), ("f", Value::from(1)), ("sin", Value::from(1.0))] {
+            let e = engine.set_global(name, value).expect_err(name);
+            assert_eq!(e.kind(), ErrorKind::Type, "{name}");
+        }
+        //the native is still there
+        assert_eq!(engine.run_to_string("<test>", "sin(0.0)".to_string()).unwrap().as_deref(), Some("0.0"));
+    }
+
+    #[test]
+    fn host_functions_are_type_checked_and_their_errors_point_at_the_call() {
+        let mut engine = Engine::new();
+        engine.register_fn("move_to", &[ValueType::Float, ValueType::Float], ValueType::Bool, |args| match args {
+            [Value::Float(x), Value::Float(_)] if *x < 0.0 => Err(format!("x = {x} is out of reach")),
+            _ => Ok(Value::Bool(true)),
+        });
+        engine.register_variadic_fn("join", &[ValueType::Str], ValueType::Int, ValueType::Str, |args| {
+            let [Value::Str(sep), rest @ ..] = args else { return Err("no separator".to_string()) };
+            Ok(Value::Str(rest.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(sep)))
+        });
+        engine.register_fn("broken", &[], ValueType::Int, |_| Ok(Value::Str("1".to_string())));
+
+        assert_eq!(engine.eval("move_to(1.0, 2.0)").unwrap(), Value::Bool(true));
+        assert_eq!(engine.eval("join(\", \")").unwrap(), Value::Str("".to_string()));
+        assert_eq!(engine.eval("join(\"-\", 1, 2, 3)").unwrap(), Value::Str("1-2-3".to_string()));
+
+        //the arguments are checked before the script runs
+        for code in ["move_to(1.0)", "move_to(true, 2.0)", "move_to(1 + 1, 2.0)", "let a: int = move_to(1.0, 2.0)", "join()", "join(\"-\", 1, 2.0)"] {
+            assert_eq!(engine.eval(code).expect_err(code).kind(), ErrorKind::Type, "{code}");
+        }
+
+        let e = engine.eval("let ok: bool = true;\nmove_to(-1.0, 0.0)").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Runtime);
+        let message = e.render(false);
+        assert!(message.contains("Call to `move_to` failed: x = -1 is out of reach"), "{message}");
+        assert!(message.contains("--> <eval>#10:2:1"), "{message}");
+
+        let message = engine.eval("broken()").unwrap_err().render(false);
+        assert!(message.contains("returned `string` instead of `int`"), "{message}");
+
+        //host functions are not forgotten by a reset
+        engine.reset();
+        assert_eq!(engine.eval("move_to(0.0, 0.0)").unwrap(), Value::Bool(true));
+    }
+}
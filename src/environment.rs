@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use crate::object::Object;
+
+//stack of variable frames, one per lexical scope; the first frame holds the globals
+#[derive(Clone)]
+pub struct Environment {
+    frames: Vec<HashMap<String, Object>>,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self { frames: vec![HashMap::new()] }
+    }
+
+    pub fn push(&mut self) {
+        self.frames.push(HashMap::new());
+    }
+
+    //drops the innermost frame and all variables declared in it; the global frame is never dropped
+    pub fn pop(&mut self) {
+        if self.frames.len() > 1 {
+            self.frames.pop();
+        }
+    }
+
+    //declares the variable in the innermost frame, shadowing any outer one with the same name
+    pub fn define(&mut self, name: &str, obj: Object) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.insert(name.to_string(), obj);
+        }
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<&Object> {
+        self.frames[0].get(name)
+    }
+
+    pub fn define_global(&mut self, name: &str, obj: Object) {
+        self.frames[0].insert(name.to_string(), obj);
+    }
+
+    //starts a function call: only the globals stay visible, the locals of the caller are returned to be restored by leave_call
+    pub fn enter_call(&mut self) -> Vec<HashMap<String, Object>> {
+        let locals = self.frames.split_off(1);
+        self.frames.push(HashMap::new());
+        locals
+    }
+
+    pub fn leave_call(&mut self, locals: Vec<HashMap<String, Object>>) {
+        self.frames.truncate(1);
+        self.frames.extend(locals);
+    }
+
+    //names of all the variables and functions currently visible
+    pub fn names(&self) -> Vec<String> {
+        self.vars().into_iter().map(|(name, _)| name).collect()
+    }
+
+    //all the visible variables and functions with their values, sorted by name
+    pub fn vars(&self) -> Vec<(String, Object)> {
+        let mut vars: Vec<(String, Object)> = vec![];
+        for (name, obj) in self.frames.iter().rev().flat_map(|frame| frame.iter()) {
+            if !vars.iter().any(|(n, _)| n == name) { vars.push((name.clone(), obj.clone())) }
+        }
+        vars.sort_by(|(a, _), (b, _)| a.cmp(b));
+        vars
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Object> {
+        self.frames.iter().rev().find_map(|frame| frame.get(name))
+    }
+
+    //updates the innermost visible variable with the given name; returns false if there is none
+    pub fn assign(&mut self, name: &str, obj: Object) -> bool {
+        match self.frames.iter_mut().rev().find_map(|frame| frame.get_mut(name)) {
+            Some(var) => { *var = obj; true },
+            None => false
+        }
+    }
+}
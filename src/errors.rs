@@ -2,29 +2,80 @@ use std::error::Error;
 use std::fmt::{self, Formatter};
 
 use crate::token::Token;
+use crate::numeric::{IntKind, MAX_FRAC_BITS, SIZED_INTS};
+use crate::span::{Span, Spanned};
+use crate::units::BASE_UNITS;
+use crate::diagnostics::{Diagnostic, ToDiagnostic};
 
 
 #[derive(Debug)]
 pub enum ParseError {
-    UnknownToken(usize, char),
-    WrongToken(usize, Token, Token),
-    UnexpectedToken(usize, Token),
-    UninitializedValue,
-    InternalError,
-    WaitForInput,
-    UndefinedSymbol(String)
+    UnknownToken(Span, char),
+    WrongToken(Span, Token, Token),
+    UnexpectedToken(Span, Token),
+    //a token found where an operand was expected
+    ExpectedExpression(Span, Token),
+    UninitializedValue(Span),
+    InternalError(Span),
+    //the input ends before the end of the statement: where it ends, and the innermost delimiter left open
+    WaitForInput(Span, Option<Spanned<Token>>),
+    UndefinedSymbol(Span, String),
+    UnterminatedString(Span),
+    InvalidEscape(Span, String),
+    //a literal too large or too small for its type
+    LiteralOutOfRange(Span, IntKind),
+    InvalidSuffix(Span, String),
+    //a malformed number literal, with what is wrong with it
+    InvalidNumber(Span, String),
+    //the number of fractional bits of a fixed-point type
+    InvalidFixedBits(Span, i128),
+}
+
+impl ParseError {
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            Self::UnknownToken(span, _) => Some(span),
+            Self::WrongToken(span, _, _) => Some(span),
+            Self::UnexpectedToken(span, _) => Some(span),
+            Self::ExpectedExpression(span, _) => Some(span),
+            Self::UninitializedValue(span) => Some(span),
+            Self::InternalError(span) => Some(span),
+            Self::WaitForInput(span, _) => Some(span),
+            Self::UndefinedSymbol(span, _) => Some(span),
+            Self::UnterminatedString(span) => Some(span),
+            Self::InvalidEscape(span, _) => Some(span),
+            Self::LiteralOutOfRange(span, _) => Some(span),
+            Self::InvalidSuffix(span, _) => Some(span),
+            Self::InvalidNumber(span, _) => Some(span),
+            Self::InvalidFixedBits(span, _) => Some(span),
+        }
+    }
+
+    //true if the error is only caused by the input ending too early, so that more input could fix it
+    pub fn is_incomplete(&self) -> bool {
+        matches!(self,
+            Self::WaitForInput(..) | Self::UnterminatedString(_) |
+            Self::WrongToken(_, _, Token::EOF) | Self::UnexpectedToken(_, Token::EOF))
+    }
 }
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
         let fmt_str :String = match self {
-            Self::UnknownToken(_pos, c) => format!("Unknown token: {c}"),
-            Self::WrongToken(_pos, tte, ttf) => format!("Unexpected token: expecting {tte}, found {ttf}"),
-            Self::UnexpectedToken(_pos, ttf) => format!("Unexpected token: {ttf}"),
-            Self::UninitializedValue => format!("Uninitialized value accessed"),
-            Self::InternalError => "Internal Error".to_string(),
-            Self::WaitForInput => "".to_string(),
-            Self::UndefinedSymbol(sn) => sn.to_string()
+            Self::UnknownToken(_span, c) => format!("Unknown token: {c}"),
+            Self::WrongToken(_span, tte, ttf) => format!("Unexpected token: expecting {tte}, found {ttf}"),
+            Self::UnexpectedToken(_span, ttf) => format!("Unexpected token: {ttf}"),
+            Self::ExpectedExpression(_span, ttf) => format!("Unexpected token: expecting an expression, found {ttf}"),
+            Self::UninitializedValue(_span) => "Uninitialized value accessed".to_string(),
+            Self::InternalError(_span) => "Internal Error".to_string(),
+            Self::WaitForInput(..) => "Unexpected end of input".to_string(),
+            Self::UndefinedSymbol(_span, sn) => sn.to_string(),
+            Self::UnterminatedString(_span) => "Unterminated string literal".to_string(),
+            Self::InvalidEscape(_span, esc) => format!("Invalid escape sequence: {esc}"),
+            Self::LiteralOutOfRange(_span, kind) => format!("Literal out of range for `{}`", kind.name()),
+            Self::InvalidSuffix(_span, suffix) => format!("Invalid suffix `{suffix}` for a number literal"),
+            Self::InvalidFixedBits(_span, q) => format!("Invalid number of fractional bits: {q}"),
+            Self::InvalidNumber(_span, reason) => format!("Invalid number literal: {reason}"),
         };
 
         write!(f, "{fmt_str}")
@@ -33,3 +84,41 @@ impl fmt::Display for ParseError {
 
 impl Error for ParseError {}
 
+impl ToDiagnostic for ParseError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        let diag = Diagnostic::error(self.to_string());
+        let diag = match self.span() {
+            Some(span) => diag.with_span(span),
+            None => diag,
+        };
+
+        match self {
+            Self::UnknownToken(_, _) => diag.with_label("unknown character"),
+            Self::WrongToken(_, tte, _) => diag.with_label(format!("expected {tte}")),
+            Self::UnexpectedToken(_, _) => diag.with_label("unexpected token"),
+            Self::ExpectedExpression(_, _) => diag.with_label("expected an expression"),
+            Self::UninitializedValue(_) => diag.with_label("value used here"),
+            Self::InternalError(_) => diag.with_note("this is a bug in the interpreter"),
+            //pointing at the delimiter left open says more than pointing at the end of the input
+            Self::WaitForInput(end, Some(open)) => diag.with_span(&open.span)
+                .with_label(format!("unclosed {}", open.node))
+                .with_note(format!("the input ends at {end}")),
+            Self::WaitForInput(_, None) => diag.with_label("the input ends here"),
+            Self::UndefinedSymbol(_, _) => diag.with_label("not found in this scope"),
+            Self::UnterminatedString(_) => diag.with_label("string starts here").with_help("add a closing `\"`"),
+            Self::InvalidEscape(_, _) => diag.with_label("invalid escape")
+                .with_note("valid escapes are \\n \\t \\r \\0 \\\\ \\\" \\{ \\} and \\u{XXXX}"),
+            Self::LiteralOutOfRange(_, kind) => diag.with_label(format!("does not fit in `{}`", kind.name()))
+                .with_note(format!("`{}` holds values from {} to {}", kind.name(), kind.min(), kind.max())),
+            Self::InvalidSuffix(_, _) => {
+                let suffixes: Vec<&str> = SIZED_INTS.iter().map(|(name, _)| *name).collect();
+                let units: Vec<&str> = BASE_UNITS.iter().map(|(name, _)| *name).collect();
+                diag.with_label("invalid suffix")
+                    .with_note(format!("valid suffixes are the integer types i64, {} and the units {}", suffixes.join(", "), units.join(", ")))
+            }
+            Self::InvalidNumber(_, _) => diag.with_label("invalid number"),
+            Self::InvalidFixedBits(_, _) => diag.with_label("invalid fractional bits")
+                .with_note(format!("fixed-point types have from 0 to {MAX_FRAC_BITS} fractional bits")),
+        }
+    }
+}
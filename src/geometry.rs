@@ -0,0 +1,211 @@
+//the vector, rotation and pose values of kinematics
+use std::fmt::{ Display, Formatter };
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vec3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Vec3 {
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn add(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(self.x + v.x, self.y + v.y, self.z + v.z)
+    }
+
+    pub fn sub(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(self.x - v.x, self.y - v.y, self.z - v.z)
+    }
+
+    pub fn scale(&self, k: f64) -> Vec3 {
+        Vec3::new(self.x * k, self.y * k, self.z * k)
+    }
+
+    pub fn dot(&self, v: &Vec3) -> f64 {
+        self.x * v.x + self.y * v.y + self.z * v.z
+    }
+
+    pub fn cross(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(self.y * v.z - self.z * v.y, self.z * v.x - self.x * v.z, self.x * v.y - self.y * v.x)
+    }
+
+    pub fn norm(&self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    //vector of length 1 with the same direction; None for the zero vector, which has none
+    pub fn normalized(&self) -> Option<Vec3> {
+        let norm = self.norm();
+        if norm == 0.0 || !norm.is_finite() { return None }
+        Some(self.scale(1.0 / norm))
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+    }
+}
+
+//rotation, as a unit quaternion w + xi + yj + zk
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quat {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Quat {
+    pub const IDENTITY: Quat = Quat { w: 1.0, x: 0.0, y: 0.0, z: 0.0 };
+
+    //the rotation the quaternion stands for, scaled to length 1; None for the zero quaternion, which stands for none
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Option<Self> {
+        let norm = (w * w + x * x + y * y + z * z).sqrt();
+        if norm == 0.0 || !norm.is_finite() { return None }
+        Some(Self { w: w / norm, x: x / norm, y: y / norm, z: z / norm })
+    }
+
+    //rotation by `angle` radians around `axis`, counterclockwise looking against the axis; None for the zero axis
+    pub fn from_axis_angle(axis: &Vec3, angle: f64) -> Option<Self> {
+        let axis = axis.normalized()?.scale((angle / 2.0).sin());
+        Quat::new((angle / 2.0).cos(), axis.x, axis.y, axis.z)
+    }
+
+    //rotation by `q` followed by the one of self
+    pub fn mul(&self, q: &Quat) -> Quat {
+        Quat {
+            w: self.w * q.w - self.x * q.x - self.y * q.y - self.z * q.z,
+            x: self.w * q.x + self.x * q.w + self.y * q.z - self.z * q.y,
+            y: self.w * q.y - self.x * q.z + self.y * q.w + self.z * q.x,
+            z: self.w * q.z + self.x * q.y - self.y * q.x + self.z * q.w,
+        }
+    }
+
+    //the opposite rotation
+    pub fn inverse(&self) -> Quat {
+        Quat { w: self.w, x: -self.x, y: -self.y, z: -self.z }
+    }
+
+    pub fn rotate(&self, v: &Vec3) -> Vec3 {
+        //v + 2w (u x v) + 2u x (u x v), with u the vector part
+        let u = Vec3::new(self.x, self.y, self.z);
+        let t = u.cross(v).scale(2.0);
+        v.add(&t.scale(self.w)).add(&u.cross(&t))
+    }
+}
+
+//position and orientation of a frame relative to another one
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pose {
+    pub position: Vec3,
+    pub orientation: Quat,
+}
+
+impl Pose {
+    pub fn new(position: Vec3, orientation: Quat) -> Self {
+        Self { position, orientation }
+    }
+
+    //pose of a frame given relative to this one, as in base * tool
+    pub fn mul(&self, p: &Pose) -> Pose {
+        Pose::new(self.transform(&p.position), self.orientation.mul(&p.orientation))
+    }
+
+    //point given in this frame, in the frame the pose is relative to
+    pub fn transform(&self, v: &Vec3) -> Vec3 {
+        self.position.add(&self.orientation.rotate(v))
+    }
+
+    //the pose that undoes this one, so that p * inverse(p) is the identity
+    pub fn inverse(&self) -> Pose {
+        let orientation = self.orientation.inverse();
+        Pose::new(orientation.rotate(&self.position).scale(-1.0), orientation)
+    }
+}
+
+//component of a value as it is shown: rounded to 9 decimals, so that rounding errors such as 2.2e-16 show as 0
+fn component(f: f64) -> String {
+    let s = format!("{f:.9}");
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" { "0".to_string() } else { s.to_string() }
+}
+
+//values are shown as the calls that make them
+impl Display for Vec3 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "vec3({}, {}, {})", component(self.x), component(self.y), component(self.z))
+    }
+}
+
+impl Display for Quat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "quat({}, {}, {}, {})", component(self.w), component(self.x), component(self.y), component(self.z))
+    }
+}
+
+impl Display for Pose {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "pose({}, {})", self.position, self.orientation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_2;
+
+    fn turn(axis: Vec3, angle: f64) -> Quat {
+        Quat::from_axis_angle(&axis, angle).unwrap()
+    }
+
+    const X: Vec3 = Vec3 { x: 1.0, y: 0.0, z: 0.0 };
+    const Y: Vec3 = Vec3 { x: 0.0, y: 1.0, z: 0.0 };
+    const Z: Vec3 = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+
+    #[test]
+    fn vectors() {
+        assert_eq!(X.cross(&Y), Z);
+        assert_eq!(Vec3::new(3.0, 4.0, 0.0).norm(), 5.0);
+        assert_eq!(Vec3::new(0.0, 0.0, 2.0).normalized(), Some(Z));
+        assert_eq!(Vec3::new(0.0, 0.0, 0.0).normalized(), None);
+    }
+
+    #[test]
+    fn quaternions_are_normalized() {
+        assert_eq!(Quat::new(2.0, 0.0, 0.0, 0.0), Some(Quat::IDENTITY));
+        assert_eq!(Quat::new(1.0, 1.0, 1.0, 1.0).unwrap().to_string(), "quat(0.5, 0.5, 0.5, 0.5)");
+        assert_eq!(Quat::new(0.0, 0.0, 0.0, 0.0), None);
+        assert_eq!(Quat::from_axis_angle(&Vec3::new(0.0, 0.0, 0.0), 1.0), None);
+    }
+
+    #[test]
+    fn rotations_are_counterclockwise() {
+        assert_eq!(turn(Z, FRAC_PI_2).rotate(&X).to_string(), "vec3(0, 1, 0)");
+        assert_eq!(turn(X, FRAC_PI_2).rotate(&Y).to_string(), "vec3(0, 0, 1)");
+        assert_eq!(turn(Y, FRAC_PI_2).rotate(&Z).to_string(), "vec3(1, 0, 0)");
+        assert_eq!(turn(Z, -FRAC_PI_2).rotate(&X).to_string(), "vec3(0, -1, 0)");
+    }
+
+    #[test]
+    fn products_apply_the_right_rotation_first() {
+        let q = turn(X, FRAC_PI_2).mul(&turn(Z, FRAC_PI_2));
+        //X is turned to Y by the rotation around Z, then to Z by the one around X
+        assert_eq!(q.rotate(&X).to_string(), "vec3(0, 0, 1)");
+        assert_eq!(q.mul(&q.inverse()).to_string(), "quat(1, 0, 0, 0)");
+        assert_eq!(q.inverse().rotate(&q.rotate(&Y)).to_string(), "vec3(0, 1, 0)");
+    }
+
+    #[test]
+    fn poses() {
+        let base = Pose::new(Vec3::new(100.0, 0.0, 0.0), turn(Z, FRAC_PI_2));
+        let tool = Pose::new(Vec3::new(10.0, 0.0, 5.0), Quat::IDENTITY);
+        assert_eq!(base.transform(&X).to_string(), "vec3(100, 1, 0)");
+        assert_eq!(base.mul(&tool).to_string(), "pose(vec3(100, 10, 5), quat(0.707106781, 0, 0, 0.707106781))");
+        assert_eq!(base.mul(&base.inverse()).to_string(), "pose(vec3(0, 0, 0), quat(1, 0, 0, 0))");
+        assert_eq!(base.inverse().mul(&base).to_string(), "pose(vec3(0, 0, 0), quat(1, 0, 0, 0))");
+        assert_eq!(base.inverse().transform(&base.transform(&Y)).to_string(), "vec3(0, 1, 0)");
+    }
+}
@@ -1,145 +1,959 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
 use crate::errors::ParseError;
-use crate::object::Object;
+use crate::object::{NativeFn, Object};
+use crate::stdlib;
+use crate::numeric::{checked_pow, fixed_from_f64, fixed_from_int, fixed_rescale, fixed_to_f64, IntKind};
 use crate::{ast::ast::*, parser::Parser};
-use std::collections::HashMap;
 use crate::token::Token;
-use crate::symbols::{self, SymbolError, SymbolTableBuilder};
+use crate::symbols::{SymbolError, SymbolTableBuilder};
+use crate::span::Span;
+use crate::units::Unit;
+use crate::environment::Environment;
+use crate::diagnostics::{Diagnostic, SourceMap, ToDiagnostic};
+use crate::ast::pretty::describe_stmt;
 
 #[derive(Debug)]
 pub enum InterpreterError {
-    SomeError,
-    ParseError(ParseError),
-    SymbolError(SymbolError)
+    //boxed so that the results threaded through every visit stay small
+    ParseError(Box<ParseError>),
+    SymbolError(Box<SymbolError>),
+    DivisionByZero(Span),
+    //overflow of an integer type, or of a fixed-point type with the given number of fractional bits
+    IntegerOverflow(Span, IntKind),
+    FixedOverflow(Span, u8),
+    //a float operation produced NaN or an infinity
+    NonFiniteFloat(Span),
+    TypeError { message: String, span: Span },
+    UndefinedVariable { name: String, span: Span },
+    IndexOutOfBounds { index: i64, len: usize, span: Span },
+    //integer raised to a negative power
+    NegativeExponent(Span),
+    //calls nested deeper than the limit, which is given
+    StackOverflow(Span, usize),
+    //every parse and symbol error found in code that was not run because of them
+    CheckFailed(Vec<InterpreterError>),
+    //a native function reported an error, or returned a value of the wrong type
+    NativeError { name: String, message: String, span: Span },
+}
+
+impl InterpreterError {
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            Self::ParseError(e) => e.span(),
+            Self::SymbolError(e) => Some(e.span()),
+            Self::DivisionByZero(span) => Some(span),
+            Self::IntegerOverflow(span, _) | Self::FixedOverflow(span, _) => Some(span),
+            Self::NonFiniteFloat(span) => Some(span),
+            Self::TypeError { span, .. } => Some(span),
+            Self::UndefinedVariable { span, .. } => Some(span),
+            Self::IndexOutOfBounds { span, .. } => Some(span),
+            Self::NegativeExponent(span) => Some(span),
+            Self::StackOverflow(span, _) => Some(span),
+            Self::CheckFailed(_) => None,
+            Self::NativeError { span, .. } => Some(span),
+        }
+    }
+
+    //true if the code only failed because it is not finished yet
+    pub fn is_incomplete(&self) -> bool {
+        match self {
+            Self::ParseError(e) => e.is_incomplete(),
+            Self::CheckFailed(errors) => errors.iter().all(Self::is_incomplete),
+            _ => false,
+        }
+    }
+
+    //prints every diagnostic of the error, with the lines they point into taken from `sources`
+    pub fn emit(&self, sources: &SourceMap) {
+        for diag in self.diagnostics() {
+            diag.emit(sources);
+        }
+    }
+
+    //one diagnostic per error, followed by a summary if there are several
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            Self::CheckFailed(errors) if errors.len() == 1 => errors[0].diagnostics(),
+            Self::CheckFailed(errors) => {
+                let mut res: Vec<Diagnostic> = errors.iter().flat_map(Self::diagnostics).collect();
+                res.push(self.to_diagnostic());
+                res
+            }
+            _ => vec![self.to_diagnostic()],
+        }
+    }
+}
+
+impl From<ParseError> for InterpreterError {
+    fn from(e: ParseError) -> Self {
+        Self::ParseError(Box::new(e))
+    }
+}
+
+impl From<SymbolError> for InterpreterError {
+    fn from(e: SymbolError) -> Self {
+        Self::SymbolError(Box::new(e))
+    }
+}
+
+impl ToDiagnostic for InterpreterError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        let diag = match self {
+            Self::ParseError(e) => return e.to_diagnostic(),
+            Self::SymbolError(e) => return e.to_diagnostic(),
+            Self::DivisionByZero(_) =>
+                Diagnostic::error("Attempt to divide by zero")
+                    .with_label("division by zero"),
+            Self::IntegerOverflow(_, kind) =>
+                Diagnostic::error("Integer overflow")
+                    .with_label("this operation overflows")
+                    .with_note(format!("`{}` values must be between {} and {}", kind.name(), kind.min(), kind.max())),
+            Self::FixedOverflow(_, q) =>
+                Diagnostic::error("Fixed-point overflow")
+                    .with_label("this operation overflows")
+                    .with_note(format!("`fixed<{q}>` values must be between {} and {}",
+                        fixed_to_f64(i64::MIN, *q), fixed_to_f64(i64::MAX, *q))),
+            Self::NonFiniteFloat(_) =>
+                Diagnostic::error("Float result is not a finite number")
+                    .with_label("evaluates to NaN or infinity"),
+            Self::TypeError { message, .. } =>
+                Diagnostic::error(message.clone())
+                    .with_label("invalid types"),
+            Self::UndefinedVariable { name, .. } =>
+                Diagnostic::error(format!("Variable `{name}` has no value"))
+                    .with_label("used here"),
+            Self::IndexOutOfBounds { index, len, .. } =>
+                Diagnostic::error(format!("Index out of bounds: the length is {len} but the index is {index}"))
+                    .with_label("index out of bounds"),
+            Self::NegativeExponent(_) =>
+                Diagnostic::error("Negative exponent in integer power")
+                    .with_label("the exponent is negative")
+                    .with_help("convert the base to `float` to get a fractional result"),
+            Self::StackOverflow(_, depth) =>
+                Diagnostic::error("Stack overflow")
+                    .with_label(format!("more than {depth} nested calls"))
+                    .with_help("check that the recursion has a reachable base case"),
+            Self::CheckFailed(errors) =>
+                Diagnostic::error(format!("Aborting due to {} previous errors", errors.len())),
+            Self::NativeError { name, message, .. } =>
+                Diagnostic::error(format!("Call to `{name}` failed: {message}"))
+                    .with_label("in this call"),
+        };
+
+        match self.span() {
+            Some(span) => diag.with_span(span),
+            None => diag,
+        }
+    }
 }
 
+//nesting of calls allowed by default; each script call takes about 4KB of the stack of the host thread in release builds
+//and ten times more in debug builds, so this fits in the 2MB of a spawned thread
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 200;
+
 pub struct Interpreter {
     parser: Parser,
     symtable: SymbolTableBuilder,
-    global_scope: HashMap<String, Object>
+    env: Environment,
+    call_depth: usize,
+    max_call_depth: usize,
+    //print every statement before running it
+    trace: bool,
+    //functions and constants registered by the host or the standard library, kept across resets
+    natives: Vec<Rc<NativeFn>>,
+    constants: Vec<(String, Object)>,
+    //text of every chunk fed, to render the diagnostics pointing into code defined by an earlier one
+    sources: SourceMap,
+    //name of the file the next chunks come from, and the number of chunks fed under each name
+    file: String,
+    chunks: HashMap<String, usize>,
 }
 
 
 impl Interpreter {
     pub fn new(parser: Parser) -> Self {
-        Self { parser, global_scope: HashMap::new(), symtable: SymbolTableBuilder::new() } }
+        let mut res = Self {
+            parser, env: Environment::new(), symtable: SymbolTableBuilder::new(), call_depth: 0, max_call_depth: DEFAULT_MAX_CALL_DEPTH, trace: false,
+            natives: vec![], constants: vec![], sources: SourceMap::new(),
+            file: "<stdin>".to_string(), chunks: HashMap::new()
+        };
+        stdlib::load(&mut res);
+        res
+    }
 
     pub fn interpret(&mut self) -> Result<Object, InterpreterError> {
-        let tree = match self.parser.parse() {
-            Ok(tree) => tree,
-            Err(e) => return Err(InterpreterError::ParseError(e))
-        };
-        
-        match self.symtable.check(&tree) {
-            Ok(()) => (),
-            Err(e) => return Err(InterpreterError::SymbolError(e))
+        let snapshot = (self.symtable.clone(), self.env.clone());
+        let tree = self.check()?;
+        let res = self.visit_block_stmt(&tree);
+        //code that fails has no effect: the names it declared are forgotten, so they can be declared again, and the
+        //variables it declared or assigned get back the values they had
+        if res.is_err() { (self.symtable, self.env) = snapshot; }
+        res
+    }
+
+    //parses and checks the fed text without running it; the symbols are checked even if parsing failed,
+    //on the statements that did parse, so that all the errors are reported at once.
+    //The checker converts the int literals used as floats, so the tree returned is the one to run
+    pub fn check(&mut self) -> Result<BlockStmt, InterpreterError> {
+        let (mut tree, parse_errors) = self.parser.parse();
+        let waiting = parse_errors.iter().any(ParseError::is_incomplete);
+        let mut errors: Vec<InterpreterError> = parse_errors.into_iter().map(InterpreterError::from).collect();
+
+        //an incomplete input is not checked, more of it is still to come
+        if !waiting {
+            let snapshot = self.symtable.clone();
+            if let Err(mut symbol_errors) = self.symtable.check(&mut tree) {
+                //a variable used after a declaration that failed to parse is not reported, the declaration already is
+                let failed = self.parser.failed_decls();
+                symbol_errors.retain(|e| !matches!(e, SymbolError::UndefinedVariable { name, span }
+                    if failed.iter().any(|(decl, decl_span)| decl == name && decl_span.offset < span.offset)));
+                errors.extend(symbol_errors.into_iter().map(InterpreterError::from));
+            }
+            //nothing is declared by code that is not going to run
+            if !errors.is_empty() { self.symtable = snapshot; }
+        }
+
+        if !errors.is_empty() {
+            errors.sort_by_key(|e| e.span().map_or((usize::MAX, 0), |span| (span.line, span.col)));
+            return Err(InterpreterError::CheckFailed(errors))
+        }
+        Ok(tree)
+    }
+
+    pub fn set_file(&mut self, file: &str) {
+        self.file = file.to_string();
+    }
+
+    //text of the files fed so far, used to render diagnostics
+    pub fn sources(&self) -> &SourceMap {
+        &self.sources
+    }
+
+    //names defined by the code run so far, and the predefined ones
+    pub fn names(&self) -> Vec<String> {
+        self.env.names()
+    }
+
+    //variables and functions defined by the code run so far, with their values
+    pub fn vars(&self) -> Vec<(String, Object)> {
+        let mut vars = self.env.vars();
+        vars.retain(|(name, obj)| !self.is_predefined(name, obj));
+        vars
+    }
+
+    //true for the natives and constants that are still bound to the name they were registered with
+    fn is_predefined(&self, name: &str, obj: &Object) -> bool {
+        match obj {
+            Object::Native(_) => true,
+            _ => self.constants.iter().any(|(n, value)| n == name && value == obj),
+        }
+    }
+
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace = trace;
+    }
+
+    //calls nested deeper than `depth` fail with a stack overflow error rather than overflowing the stack of the thread
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.max_call_depth = depth;
+    }
+
+    //defines an immutable global variable holding `value`; returns false if the value can't be stored in a variable
+    pub fn define_global(&mut self, name: &str, value: Object) -> bool {
+        let Some(t) = value.type_token() else { return false };
+        self.symtable.define_global(name, Type::new(t, false));
+        self.env.define_global(name, value);
+        true
+    }
+
+    //type of the global variable with the given name, as declared
+    pub fn global_type(&self, name: &str) -> Option<Type> {
+        self.symtable.global_type(name)
+    }
+
+    //value of the global variable or function with the given name
+    pub fn get_global(&self, name: &str) -> Option<&Object> {
+        self.env.get_global(name)
+    }
+
+    //changes the value of an existing global; the caller makes sure the value has the declared type
+    pub fn set_global(&mut self, name: &str, value: Object) {
+        self.env.define_global(name, value);
+    }
+
+    //forgets everything defined by the code run so far
+    pub fn reset(&mut self) {
+        self.env = Environment::new();
+        self.symtable = SymbolTableBuilder::new();
+        self.call_depth = 0;
+        for native in self.natives.clone() {
+            self.declare_native(native);
+        }
+        for (name, value) in self.constants.clone() {
+            self.define_global(&name, value);
         }
-        Ok(self.visit_block_stmt(&tree)?)
     }
 
+    //defines an immutable global that survives resets; returns false if the value can't be stored in a variable
+    pub fn register_const(&mut self, name: &str, value: Object) -> bool {
+        if !self.define_global(name, value.clone()) { return false }
+        self.constants.retain(|(n, _)| n != name);
+        self.constants.push((name.to_string(), value));
+        true
+    }
+
+    //makes a Rust function callable from the code; registering a name again replaces the function
+    pub fn register_fn(&mut self, native: NativeFn) {
+        let native = Rc::new(native);
+        self.natives.retain(|n| n.name != native.name);
+        self.natives.push(native.clone());
+        self.declare_native(native);
+    }
+
+    fn declare_native(&mut self, native: Rc<NativeFn>) {
+        let name = native.name.clone();
+        self.symtable.define_native(&name, native.signatures.clone());
+        self.env.define_global(&name, Object::Native(native));
+    }
+
+    //parses the fed text without checking or running it
+    pub fn parse(&mut self) -> Result<BlockStmt, InterpreterError> {
+        let (tree, errors) = self.parser.parse();
+        if !errors.is_empty() {
+            return Err(InterpreterError::CheckFailed(errors.into_iter().map(InterpreterError::from).collect()))
+        }
+        Ok(tree)
+    }
+
+    //infers the type of the value of the fed text without running it; None if it always returns early
+    pub fn type_of(&mut self) -> Result<Option<Type>, InterpreterError> {
+        let mut tree = self.parse()?;
+        self.symtable.infer(&mut tree).map_err(InterpreterError::SymbolError)
+    }
+
+    //each new chunk of text fed as the same file gets a name of its own, file#2, file#3 and so on, so that the
+    //diagnostics pointing into code defined by an earlier chunk still show its text; lines appended to a statement
+    //left unfinished belong to its chunk
     pub fn feed_next_line(&mut self, line: String, append: &mut bool) {
+        if !*append {
+            let count = self.chunks.entry(self.file.clone()).or_insert(0);
+            *count += 1;
+            let name = if *count == 1 { self.file.clone() } else { format!("{}#{count}", self.file) };
+            self.parser.set_file(&name);
+        }
         self.parser.feed_next_line(line, *append);
+        self.sources.add(self.parser.file(), self.parser.source());
         *append = false;
     }
 
-    fn int_expr(lhs: &i64, token: &Token, rhs: &i64) -> Result<Object, InterpreterError> {
+    //integer arithmetic is checked; overflow and division by zero are runtime errors
+    fn int_expr(lhs: &i64, token: &Token, rhs: &i64, span: &Span) -> Result<Object, InterpreterError> {
+        use Token::*;
+
+        let res = match token {
+            Less => return Ok(Object::Bool(lhs < rhs)),
+            LessEqual => return Ok(Object::Bool(lhs <= rhs)),
+            Greater => return Ok(Object::Bool(lhs > rhs)),
+            GreaterEqual => return Ok(Object::Bool(lhs >= rhs)),
+            EqualEqual => return Ok(Object::Bool(lhs == rhs)),
+            NotEqual => return Ok(Object::Bool(lhs != rhs)),
+            Plus => lhs.checked_add(*rhs),
+            Minus => lhs.checked_sub(*rhs),
+            Multiply => lhs.checked_mul(*rhs),
+            Divide => {
+                if *rhs == 0 { return Err(InterpreterError::DivisionByZero(span.clone())) }
+                lhs.checked_div(*rhs)
+            }
+            Modulo => {
+                if *rhs == 0 { return Err(InterpreterError::DivisionByZero(span.clone())) }
+                lhs.checked_rem(*rhs)
+            }
+            Power if *rhs < 0 => return Err(InterpreterError::NegativeExponent(span.clone())),
+            Power => checked_pow(*lhs as i128, *rhs as i128).and_then(|res| i64::try_from(res).ok()),
+            BitAnd => Some(lhs & rhs),
+            BitOr => Some(lhs | rhs),
+            BitXor => Some(lhs ^ rhs),
+            //shifting by a negative amount or by the width of int or more overflows
+            ShiftLeft => u32::try_from(*rhs).ok().and_then(|r| lhs.checked_shl(r)),
+            ShiftRight => u32::try_from(*rhs).ok().and_then(|r| lhs.checked_shr(r)),
+            _ => return Err(Self::operator_error(token, "int", span))
+        };
+
+        match res {
+            Some(i) => Ok(Object::Int(i)),
+            None => Err(InterpreterError::IntegerOverflow(span.clone(), IntKind::I64))
+        }
+    }
+
+    //like int arithmetic, on values of a sized integer type; the bitwise operators can't overflow, the bits shifted
+    //out are lost and the shift amount must be smaller than the width of the type
+    fn sized_expr(lhs: i128, token: &Token, rhs: i128, kind: IntKind, span: &Span) -> Result<Object, InterpreterError> {
+        use Token::*;
+
+        let res = match token {
+            Less => return Ok(Object::Bool(lhs < rhs)),
+            LessEqual => return Ok(Object::Bool(lhs <= rhs)),
+            Greater => return Ok(Object::Bool(lhs > rhs)),
+            GreaterEqual => return Ok(Object::Bool(lhs >= rhs)),
+            EqualEqual => return Ok(Object::Bool(lhs == rhs)),
+            NotEqual => return Ok(Object::Bool(lhs != rhs)),
+            Plus => lhs.checked_add(rhs),
+            Minus => lhs.checked_sub(rhs),
+            Multiply => lhs.checked_mul(rhs),
+            Divide | Modulo if rhs == 0 => return Err(InterpreterError::DivisionByZero(span.clone())),
+            Divide => lhs.checked_div(rhs),
+            Modulo => lhs.checked_rem(rhs),
+            Power if rhs < 0 => return Err(InterpreterError::NegativeExponent(span.clone())),
+            Power => checked_pow(lhs, rhs),
+            BitAnd => Some(lhs & rhs),
+            BitOr => Some(lhs | rhs),
+            BitXor => Some(lhs ^ rhs),
+            ShiftLeft | ShiftRight if !(0..kind.bits() as i128).contains(&rhs) => None,
+            ShiftLeft => Some(kind.wrap(lhs << rhs)),
+            ShiftRight => Some(lhs >> rhs),
+            _ => return Err(Self::operator_error(token, kind.name(), span))
+        };
+
+        match res {
+            Some(i) if kind.contains(i) => Ok(Object::SizedInt(i, kind)),
+            _ => Err(InterpreterError::IntegerOverflow(span.clone(), kind))
+        }
+    }
+
+    //arithmetic on the raw values of fixed-point numbers with `q` fractional bits; products are rounded down and
+    //quotients toward zero, overflow and division by zero are runtime errors
+    fn fixed_expr(lhs: i64, token: &Token, rhs: i64, q: u8, span: &Span) -> Result<Object, InterpreterError> {
         use Token::*;
-        use Object::Int;
 
-        match token {
-            Plus => return Ok(Int(lhs + rhs)), 
-            Minus => return Ok(Int(lhs - rhs)),
-            Multiply => return Ok(Int(lhs * rhs)),
-            Divide => return Ok(Int(lhs / rhs)),
-            _ => return Err(InterpreterError::SomeError)
+        let (l, r) = (lhs as i128, rhs as i128);
+        let res = match token {
+            Less => return Ok(Object::Bool(lhs < rhs)),
+            LessEqual => return Ok(Object::Bool(lhs <= rhs)),
+            Greater => return Ok(Object::Bool(lhs > rhs)),
+            GreaterEqual => return Ok(Object::Bool(lhs >= rhs)),
+            EqualEqual => return Ok(Object::Bool(lhs == rhs)),
+            NotEqual => return Ok(Object::Bool(lhs != rhs)),
+            Plus => l + r,
+            Minus => l - r,
+            Multiply => (l * r) >> q,
+            Divide | Modulo if rhs == 0 => return Err(InterpreterError::DivisionByZero(span.clone())),
+            Divide => (l << q) / r,
+            Modulo => l % r,
+            _ => return Err(Self::operator_error(token, &format!("fixed<{q}>"), span))
+        };
+
+        match i64::try_from(res) {
+            Ok(raw) => Ok(Object::Fixed(raw, q)),
+            Err(_) => Err(InterpreterError::FixedOverflow(span.clone(), q))
         }
     }
 
-    fn float_expr(lhs: &f64, token: &Token, rhs: &f64) -> Result<Object, InterpreterError> {
+    //float arithmetic follows IEEE-754, except that division by zero and results that are NaN or infinite are runtime errors
+    fn float_expr(lhs: &f64, token: &Token, rhs: &f64, span: &Span) -> Result<Object, InterpreterError> {
         use Token::*;
-        use Object::Float;
 
-        match token {
-            Plus => return Ok(Float(lhs + rhs)), 
-            Minus => return Ok(Float(lhs - rhs)),
-            Multiply => return Ok(Float(lhs * rhs)),
-            Divide => return Ok(Float(lhs / rhs)),
-            _ => return Err(InterpreterError::SomeError)
+        let res = match token {
+            Less => return Ok(Object::Bool(lhs < rhs)),
+            LessEqual => return Ok(Object::Bool(lhs <= rhs)),
+            Greater => return Ok(Object::Bool(lhs > rhs)),
+            GreaterEqual => return Ok(Object::Bool(lhs >= rhs)),
+            EqualEqual => return Ok(Object::Bool(lhs == rhs)),
+            NotEqual => return Ok(Object::Bool(lhs != rhs)),
+            Plus => lhs + rhs,
+            Minus => lhs - rhs,
+            Multiply => lhs * rhs,
+            Divide => {
+                if *rhs == 0.0 { return Err(InterpreterError::DivisionByZero(span.clone())) }
+                lhs / rhs
+            }
+            Modulo => {
+                if *rhs == 0.0 { return Err(InterpreterError::DivisionByZero(span.clone())) }
+                lhs % rhs
+            }
+            Power => lhs.powf(*rhs),
+            _ => return Err(Self::operator_error(token, "float", span))
+        };
+
+        Self::finite(res, span)
+    }
+
+    fn finite(f: f64, span: &Span) -> Result<Object, InterpreterError> {
+        if !f.is_finite() { return Err(InterpreterError::NonFiniteFloat(span.clone())) }
+        Ok(Object::Float(f))
+    }
+
+    //the checker converts the operands to matching units: the same one for addition and comparisons, and aligned ones
+    //for multiplication and division
+    fn quantity_expr(lhs: f64, lunit: &Unit, token: &Token, rhs: f64, runit: &Unit, span: &Span) -> Result<Object, InterpreterError> {
+        let unit = match token {
+            Token::Multiply => lunit.mul(runit),
+            Token::Divide => lunit.div(runit),
+            Token::Power => return Err(Self::operator_error(token, &format!("`float<{lunit}>`"), span)),
+            _ if lunit == runit => lunit.clone(),
+            _ => return Err(Self::operator_error(token, &format!("`float<{lunit}>` and `float<{runit}>`"), span)),
+        };
+
+        match Self::float_expr(&lhs, token, &rhs, span)? {
+            Object::Float(f) => Ok(Object::quantity(f, unit)),
+            res => Ok(res),
         }
     }
 
-    fn visit_infix(lhs: &Object, token: &Token, rhs: &Object) -> Result<Object, InterpreterError> {
+    //vectors are added and scaled, rotations and poses composed and applied to vectors with `*`
+    fn geometry_expr(lhs: &Object, token: &Token, rhs: &Object, span: &Span) -> Result<Object, InterpreterError> {
+        use Object::*;
+
+        let res = match (lhs, token, rhs) {
+            (Vec3(a), Token::Plus, Vec3(b)) => Vec3(a.add(b)),
+            (Vec3(a), Token::Minus, Vec3(b)) => Vec3(a.sub(b)),
+            (Vec3(v), Token::Multiply, Float(k)) | (Float(k), Token::Multiply, Vec3(v)) => Vec3(v.scale(*k)),
+            (Vec3(_), Token::Divide, Float(k)) if *k == 0.0 => return Err(InterpreterError::DivisionByZero(span.clone())),
+            (Vec3(v), Token::Divide, Float(k)) => Vec3(v.scale(1.0 / k)),
+            (Quat(a), Token::Multiply, Quat(b)) => Quat(a.mul(b)),
+            (Pose(a), Token::Multiply, Pose(b)) => Pose(a.mul(b)),
+            (Quat(q), Token::Multiply, Vec3(v)) => Vec3(q.rotate(v)),
+            (Pose(p), Token::Multiply, Vec3(v)) => Vec3(p.transform(v)),
+            (_, Token::EqualEqual, _) if lhs.type_name() == rhs.type_name() => Bool(lhs == rhs),
+            (_, Token::NotEqual, _) if lhs.type_name() == rhs.type_name() => Bool(lhs != rhs),
+            _ => return Err(Self::operator_error(token, &format!("`{}` and `{}`", lhs.type_name(), rhs.type_name()), span))
+        };
+
+        match res {
+            Vec3(v) if !v.is_finite() => Err(InterpreterError::NonFiniteFloat(span.clone())),
+            res => Ok(res),
+        }
+    }
+
+    //fields of vectors, rotations and poses; the ones of the position are also fields of a pose
+    fn visit_field(value: &Object, name: &str, span: &Span) -> Result<Object, InterpreterError> {
+        let v = match value {
+            Object::Vec3(v) => Some(v),
+            Object::Pose(p) => Some(&p.position),
+            _ => None,
+        };
+
+        let res = match (value, name, v) {
+            (Object::Pose(p), "position", _) => Object::Vec3(p.position),
+            (Object::Pose(p), "orientation", _) => Object::Quat(p.orientation),
+            (Object::Quat(q), "w", _) => Object::Float(q.w),
+            (Object::Quat(q), "x", _) => Object::Float(q.x),
+            (Object::Quat(q), "y", _) => Object::Float(q.y),
+            (Object::Quat(q), "z", _) => Object::Float(q.z),
+            (_, "x", Some(v)) => Object::Float(v.x),
+            (_, "y", Some(v)) => Object::Float(v.y),
+            (_, "z", Some(v)) => Object::Float(v.z),
+            _ => return Err(InterpreterError::TypeError {
+                message: format!("No field `{name}` on type `{}`", value.type_name()),
+                span: span.clone()
+            })
+        };
+        Ok(res)
+    }
+
+    fn operator_error(token: &Token, operands: &str, span: &Span) -> InterpreterError {
+        InterpreterError::TypeError { message: format!("Cannot apply {token} to {operands}"), span: span.clone() }
+    }
+
+    fn visit_infix(lhs: &Object, token: &Token, rhs: &Object, span: &Span) -> Result<Object, InterpreterError> {
         use Object::*;
 
         match (lhs, rhs) {
-            (Int(i1), Int(i2)) => Self::int_expr(i1, token, i2),
-            (Float(f1), Float(f2)) => Self::float_expr(f1, token, f2),
-            (_, _) => Err(InterpreterError::SomeError)
+            (Int(i1), Int(i2)) => Self::int_expr(i1, token, i2, span),
+            (Float(f1), Float(f2)) => Self::float_expr(f1, token, f2, span),
+            (SizedInt(i1, k1), SizedInt(i2, k2)) if k1 == k2 => Self::sized_expr(*i1, token, *i2, *k1, span),
+            (Fixed(r1, q1), Fixed(r2, q2)) if q1 == q2 => Self::fixed_expr(*r1, token, *r2, *q1, span),
+            (Quantity(f1, u1), Quantity(f2, u2)) => Self::quantity_expr(*f1, u1, token, *f2, u2, span),
+            (Quantity(f1, u1), Float(f2)) => Self::quantity_expr(*f1, u1, token, *f2, &Unit::default(), span),
+            (Float(f1), Quantity(f2, u2)) => Self::quantity_expr(*f1, &Unit::default(), token, *f2, u2, span),
+            (Vec3(_) | Quat(_) | Pose(_), _) | (_, Vec3(_) | Quat(_) | Pose(_)) => Self::geometry_expr(lhs, token, rhs, span),
+            (Str(s1), Str(s2)) => match token {
+                Token::Plus => Ok(Str(format!("{s1}{s2}"))),
+                Token::EqualEqual => Ok(Bool(s1 == s2)),
+                Token::NotEqual => Ok(Bool(s1 != s2)),
+                Token::Less => Ok(Bool(s1 < s2)),
+                Token::LessEqual => Ok(Bool(s1 <= s2)),
+                Token::Greater => Ok(Bool(s1 > s2)),
+                Token::GreaterEqual => Ok(Bool(s1 >= s2)),
+                _ => Err(Self::operator_error(token, "string", span))
+            },
+            (Bool(b1), Bool(b2)) => match token {
+                Token::EqualEqual => Ok(Bool(b1 == b2)),
+                Token::NotEqual => Ok(Bool(b1 != b2)),
+                Token::And => Ok(Bool(*b1 && *b2)),
+                Token::Or => Ok(Bool(*b1 || *b2)),
+                _ => Err(Self::operator_error(token, "bool", span))
+            },
+            (_, _) => Err(Self::operator_error(token, &format!("`{}` and `{}`", lhs.type_name(), rhs.type_name()), span))
         }
     }
 
-    fn visit_prefix(token: &Token, rhs: &Object) -> Result<Object, InterpreterError> {
+    fn visit_prefix(token: &Token, rhs: &Object, span: &Span) -> Result<Object, InterpreterError> {
         use Object::*;
 
-        match token {
-            Token::Minus => match rhs {
-                Int(i) => return Ok(Int(-i)),
-                Float(f) => return Ok(Float(-f)),
-                _ => return Err(InterpreterError::SomeError)
-            }
+        match (token, rhs) {
+            (Token::Minus, Int(i)) => match i.checked_neg() {
+                Some(i) => Ok(Int(i)),
+                None => Err(InterpreterError::IntegerOverflow(span.clone(), IntKind::I64))
+            },
+            (Token::Minus, SizedInt(i, kind)) if kind.contains(-i) => Ok(SizedInt(-i, *kind)),
+            (Token::Minus, SizedInt(_, kind)) => Err(InterpreterError::IntegerOverflow(span.clone(), *kind)),
+            (Token::Minus, Float(f)) => Ok(Float(-f)),
+            (Token::Minus, Quantity(f, unit)) => Ok(Quantity(-f, unit.clone())),
+            (Token::Minus, Vec3(v)) => Ok(Vec3(v.scale(-1.0))),
+            (Token::Minus, Fixed(raw, q)) => match raw.checked_neg() {
+                Some(raw) => Ok(Fixed(raw, *q)),
+                None => Err(InterpreterError::FixedOverflow(span.clone(), *q))
+            },
+            (Token::Not, Bool(b)) => Ok(Bool(!b)),
+            (Token::BitNot, Int(i)) => Ok(Int(!i)),
+            (Token::BitNot, SizedInt(i, kind)) => Ok(SizedInt(kind.wrap(!i), *kind)),
+            _ => Err(Self::operator_error(token, &format!("`{}`", rhs.type_name()), span))
+        }
+    }
 
-            _ => Err(InterpreterError::SomeError)
+    //integers cast to a smaller integer type keep their low bits; floats and fixed-point numbers cast to an integer
+    //type are rounded toward zero, and numbers cast to a fixed-point type are rounded to the nearest value, both
+    //saturating at the smallest and largest value of the type. Floats with a unit are converted to other units of
+    //the same dimensions, and cast to other types by dropping the unit
+    fn visit_cast(value: Object, t: &Type, span: &Span) -> Result<Object, InterpreterError> {
+        use Object::*;
+
+        let to_kind = match t.t {
+            Token::IntType => Some(IntKind::I64),
+            Token::SizedIntType(kind) => Some(kind),
+            _ => None,
+        };
+
+        //the unit is kept only when converting to another unit
+        let value = match value {
+            Quantity(f, _) if !matches!(t.t, Token::QuantityType(_)) => Float(f),
+            value => value,
+        };
+
+        let res = match (&value, &t.t, to_kind) {
+            (value, t, _) if value.type_token().as_ref() == Some(t) => Some(value.clone()),
+            (value, Token::QuantityType(unit), _) => match (value.as_integer(), value) {
+                (Some((i, _)), _) => Some(Quantity(i as f64, unit.clone())),
+                (None, Float(f)) => Some(Quantity(*f, unit.clone())),
+                (None, Fixed(raw, q)) => Some(Quantity(fixed_to_f64(*raw, *q), unit.clone())),
+                (None, Quantity(f, from)) => from.factor_to(unit).map(|k| Quantity(f * k, unit.clone())),
+                _ => None,
+            },
+            (value, _, Some(kind)) => match (value.as_integer(), value) {
+                (Some((i, _)), _) => Some(Object::integer(kind.wrap(i), kind)),
+                (None, Float(f)) => Some(Object::integer(kind.saturate(*f as i128), kind)),
+                //the division rounds toward zero
+                (None, Fixed(raw, q)) => Some(Object::integer(kind.saturate(*raw as i128 / (1i128 << q)), kind)),
+                _ => None,
+            },
+            (value, Token::FloatType, _) => match (value.as_integer(), value) {
+                (Some((i, _)), _) => Some(Float(i as f64)),
+                (None, Fixed(raw, q)) => Some(Float(fixed_to_f64(*raw, *q))),
+                _ => None,
+            },
+            (value, Token::FixedType(q), _) => match (value.as_integer(), value) {
+                (Some((i, _)), _) => Some(Fixed(fixed_from_int(i, *q), *q)),
+                (None, Float(f)) => Some(Fixed(fixed_from_f64(*f, *q), *q)),
+                (None, Fixed(raw, from)) => Some(Fixed(fixed_rescale(*raw, *from, *q), *q)),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        res.ok_or_else(|| InterpreterError::TypeError {
+            message: format!("Cannot cast `{}` as `{t}`", value.type_name()),
+            span: span.clone()
+        })
+    }
+
+    //indexing a string gives the character at that position as a string
+    fn visit_index(value: &Object, index: &Object, span: &Span) -> Result<Object, InterpreterError> {
+        match (value, index) {
+            (Object::Str(s), Object::Int(i)) => {
+                let c = usize::try_from(*i).ok().and_then(|i| s.chars().nth(i));
+                match c {
+                    Some(c) => Ok(Object::Str(c.to_string())),
+                    None => Err(InterpreterError::IndexOutOfBounds { index: *i, len: s.chars().count(), span: span.clone() })
+                }
+            }
+            _ => Err(InterpreterError::TypeError {
+                message: format!("Cannot index `{}` with `{}`", value.type_name(), index.type_name()),
+                span: span.clone()
+            })
         }
     }
 
-    pub fn visit_expr(&self, expr: &Expr) -> Result<Object, InterpreterError> {
-        use Expr::*;
+    pub fn visit_expr(&mut self, expr: &Expr) -> Result<Object, InterpreterError> {
+        use ExprKind::*;
 
-        match expr {
-            NumLit(n) => Ok(Object::Int(*n)),
+        match &expr.kind {
+            //the checker made sure that the literals left as int fit in it
+            NumLit(n) => Ok(Object::Int(*n as i64)),
+            SizedLit(n, kind) => Ok(Object::SizedInt(*n, *kind)),
+            FixedLit(raw, q) => Ok(Object::Fixed(*raw, *q)),
             FloatLit(f) => Ok(Object::Float(*f)),
+            QuantityLit(f, unit) => Ok(Object::Quantity(*f, unit.clone())),
+            BoolLit(b) => Ok(Object::Bool(*b)),
+            StrLit(s) => Ok(Object::Str(s.clone())),
+            Interp(ref parts) => self.visit_interp(parts),
             Noop => Ok(Object::Null),
             Var(ref var_id) => {
-                if let Some(obj) = self.global_scope.get(var_id) {
+                if let Some(obj) = self.env.get(var_id) {
                     return Ok(obj.clone())
                 }
-                Err(InterpreterError::SomeError)
+                Err(InterpreterError::UndefinedVariable { name: var_id.clone(), span: expr.span.clone() })
             }
 
             Infix(ref lhs, ref token, ref rhs) => {
                 let l = self.visit_expr(lhs)?;
+                if l.is_control() { return Ok(l) }
+
+                //&& and || only evaluate the right hand side if the left one doesn't decide the result
+                match (token, &l) {
+                    (Token::And, Object::Bool(false)) => return Ok(l),
+                    (Token::Or, Object::Bool(true)) => return Ok(l),
+                    _ => (),
+                }
+
                 let r = self.visit_expr(rhs)?;
-                Self::visit_infix(&l, token, &r)
+                if r.is_control() { return Ok(r) }
+                Self::visit_infix(&l, token, &r, &expr.span)
             },
 
             Prefix(ref token, ref rhs) => {
                 let r = self.visit_expr(rhs)?;
-                Self::visit_prefix(token, &r)
+                if r.is_control() { return Ok(r) }
+                Self::visit_prefix(token, &r, &expr.span)
+            }
+
+            Call(ref name, ref args) => self.visit_call(name, args, &expr.span),
+
+            Cast(ref value, ref t) => {
+                let v = self.visit_expr(value)?;
+                if v.is_control() { return Ok(v) }
+                Self::visit_cast(v, t, &expr.span)
+            }
+
+            Field(ref value, ref name) => {
+                let v = self.visit_expr(value)?;
+                if v.is_control() { return Ok(v) }
+                Self::visit_field(&v, name, &expr.span)
             }
+
+            Index(ref value, ref index) => {
+                let v = self.visit_expr(value)?;
+                if v.is_control() { return Ok(v) }
+                let i = self.visit_expr(index)?;
+                if i.is_control() { return Ok(i) }
+                Self::visit_index(&v, &i, &expr.span)
+            }
+
+            If(ref cond, ref then, ref otherwise) => self.visit_if(cond, then, otherwise.as_ref()),
+        }
+    }
+
+    //the arms of visit_expr that need more than a few locals are kept out of it, so that its frame stays small in deep
+    //recursions
+    fn visit_interp(&mut self, parts: &[Expr]) -> Result<Object, InterpreterError> {
+        let mut res = String::new();
+        for part in parts {
+            let obj = self.visit_expr(part)?;
+            if obj.is_control() { return Ok(obj) }
+            res.push_str(&obj.to_string());
+        }
+        Ok(Object::Str(res))
+    }
+
+    fn visit_call(&mut self, name: &str, args: &[Expr], span: &Span) -> Result<Object, InterpreterError> {
+        let mut values = vec![];
+        for arg in args {
+            let obj = self.visit_expr(arg)?;
+            if obj.is_control() { return Ok(obj) }
+            values.push(obj);
+        }
+
+        match self.env.get(name) {
+            Some(Object::Fn(decl)) => self.call(decl.clone(), values, span),
+            Some(Object::Native(native)) => Self::call_native(native, &values, span),
+            _ => Err(InterpreterError::UndefinedVariable { name: name.to_string(), span: span.clone() })
         }
     }
 
+    fn visit_if(&mut self, cond: &Expr, then: &BlockStmt, otherwise: Option<&BlockStmt>) -> Result<Object, InterpreterError> {
+        let branch = match self.visit_expr(cond)? {
+            Object::Bool(true) => then,
+            Object::Bool(false) => match otherwise {
+                Some(blk) => blk,
+                None => return Ok(Object::Null)
+            },
+            obj if obj.is_control() => return Ok(obj),
+            obj => return Err(InterpreterError::TypeError {
+                message: format!("Expected `bool` condition, found `{}`", obj.type_name()),
+                span: cond.span.clone()
+            })
+        };
+
+        let res = self.visit_scoped_block(branch)?;
+        //an if without else has no value
+        if otherwise.is_none() && !res.is_control() { return Ok(Object::Null) }
+        Ok(res)
+    }
+
     fn visit_stmt(&mut self, stmt: &Stmt) -> Result<Object, InterpreterError> {
-        use Stmt::*;
+        use StmtKind::*;
+
+        if self.trace { eprintln!("trace: {}: {}", stmt.span, describe_stmt(stmt)); }
 
-        match stmt {
+        match &stmt.kind {
             Expr(ref expr) => self.visit_expr(expr),
-            Return(ref expr) => Ok(Object::Return(Box::new(self.visit_expr(expr)?))),
-            Block(ref stmts) => { self.visit_block_stmt(stmts) }
+            Return(ref expr) => {
+                let obj = self.visit_expr(expr)?;
+                if obj.is_control() { return Ok(obj) }
+                Ok(Object::Return(Box::new(obj)))
+            }
+            Block(ref stmts) => self.visit_scoped_block(stmts),
+            While(ref label, ref cond, ref body) => self.visit_while(label, cond, body),
+            For(ref label, ref var, ref from, ref to, ref body) => {
+                let ExprKind::Var(id) = &var.kind else {
+                    return Err(InterpreterError::TypeError { message: "Invalid loop variable".to_string(), span: var.span.clone() })
+                };
+
+                //the loop variable lives in its own scope around the body
+                self.env.push();
+                let res = self.visit_for(label, id, from, to, body);
+                self.env.pop();
+                res
+            }
+            Loop(ref label, ref body) => {
+                loop {
+                    let res = self.visit_scoped_block(body)?;
+                    if let Some(obj) = Self::loop_control(label, res) { return Ok(obj) }
+                }
+            }
+            Fn(ref decl) => {
+                self.env.define(&decl.name, Object::Fn(decl.clone()));
+                Ok(Object::Null)
+            }
+            Break(ref label) => Ok(Object::Break(label.clone())),
+            Continue(ref label) => Ok(Object::Continue(label.clone())),
             Let(ref var, _, ref expr) => {
-                if let crate::ast::ast::Expr::Var(id) = var {
-                    self.global_scope.insert(id.to_string(), self.visit_expr(expr)?);
+                if let ExprKind::Var(id) = &var.kind {
+                    let obj = self.visit_expr(expr)?;
+                    if obj.is_control() { return Ok(obj) }
+                    self.env.define(id, obj);
                     return Ok(Object::Null);
                 }
-                Err(InterpreterError::SomeError)
+                Err(InterpreterError::TypeError { message: "Invalid assignment target".to_string(), span: var.span.clone() })
             }
             Assign(ref var, ref expr) => {
-                if let crate::ast::ast::Expr::Var(id) = var {
-                    self.global_scope.insert(id.to_string(), self.visit_expr(expr)?);
+                if let ExprKind::Var(id) = &var.kind {
+                    let obj = self.visit_expr(expr)?;
+                    if obj.is_control() { return Ok(obj) }
+                    if !self.env.assign(id, obj) {
+                        return Err(InterpreterError::UndefinedVariable { name: id.clone(), span: var.span.clone() });
+                    }
                     return Ok(Object::Null);
                 }
-                Err(InterpreterError::SomeError)
+                Err(InterpreterError::TypeError { message: "Invalid assignment target".to_string(), span: var.span.clone() })
+            }
+        }
+    }
+
+    //decides what a loop does with the value of one run of its body: None to keep looping, Some to stop and produce the value
+    fn loop_control(label: &Option<String>, res: Object) -> Option<Object> {
+        match res {
+            Object::Break(None) => Some(Object::Null),
+            Object::Break(Some(ref l)) if label.as_ref() == Some(l) => Some(Object::Null),
+            Object::Continue(None) => None,
+            Object::Continue(Some(ref l)) if label.as_ref() == Some(l) => None,
+            //return, or a break/continue targeting an outer loop
+            obj if obj.is_control() => Some(obj),
+            _ => None
+        }
+    }
+
+    fn visit_while(&mut self, label: &Option<String>, cond: &Expr, body: &BlockStmt) -> Result<Object, InterpreterError> {
+        loop {
+            match self.visit_expr(cond)? {
+                Object::Bool(true) => (),
+                Object::Bool(false) => return Ok(Object::Null),
+                obj if obj.is_control() => return Ok(obj),
+                obj => return Err(InterpreterError::TypeError {
+                    message: format!("Expected `bool` condition, found `{}`", obj.type_name()),
+                    span: cond.span.clone()
+                })
+            }
+
+            let res = self.visit_scoped_block(body)?;
+            if let Some(obj) = Self::loop_control(label, res) { return Ok(obj) }
+        }
+    }
+
+    fn visit_for(&mut self, label: &Option<String>, var: &str, from: &Expr, to: &Expr, body: &BlockStmt) -> Result<Object, InterpreterError> {
+        let mut bounds = [0; 2];
+        for (bound, expr) in bounds.iter_mut().zip([from, to]) {
+            match self.visit_expr(expr)? {
+                Object::Int(i) => *bound = i,
+                obj if obj.is_control() => return Ok(obj),
+                obj => return Err(InterpreterError::TypeError {
+                    message: format!("Expected `int` range bound, found `{}`", obj.type_name()),
+                    span: expr.span.clone()
+                })
             }
         }
+
+        for i in bounds[0]..bounds[1] {
+            self.env.define(var, Object::Int(i));
+            let res = self.visit_scoped_block(body)?;
+            if let Some(obj) = Self::loop_control(label, res) { return Ok(obj) }
+        }
+
+        Ok(Object::Null)
+    }
+
+    //runs the function body in a new call frame that only sees the globals and the parameters
+    fn call(&mut self, decl: Rc<FnDecl>, args: Vec<Object>, span: &Span) -> Result<Object, InterpreterError> {
+        if self.call_depth >= self.max_call_depth {
+            return Err(InterpreterError::StackOverflow(span.clone(), self.max_call_depth))
+        }
+
+        let locals = self.env.enter_call();
+        for (param, arg) in decl.params.iter().zip(args) {
+            self.env.define(&param.name, arg);
+        }
+
+        self.call_depth += 1;
+        let res = self.visit_block_stmt(&decl.body);
+        self.call_depth -= 1;
+        self.env.leave_call(locals);
+
+        match res? {
+            Object::Return(obj) => Ok(*obj),
+            _ if decl.ret.t == Token::Null => Ok(Object::Null),
+            obj => Ok(obj),
+        }
+    }
+
+    //the value returned by the host is checked against the declared return type
+    fn call_native(native: &NativeFn, args: &[Object], span: &Span) -> Result<Object, InterpreterError> {
+        let error = |message: String| InterpreterError::NativeError { name: native.name.clone(), message, span: span.clone() };
+        let res = (native.func)(args).map_err(error)?;
+
+        let ret = match native.signature_for(args) {
+            Some(signature) => &signature.ret,
+            None => return Ok(res),
+        };
+        let ok = match res.type_token() {
+            Some(t) => t == ret.t,
+            None => res == Object::Null && ret.t == Token::Null,
+        };
+        if !ok {
+            return Err(error(format!("returned `{}` instead of `{ret}`", res.type_name())))
+        }
+
+        Ok(res)
     }
 
     fn visit_block_stmt(&mut self, block: &BlockStmt) -> Result<Object, InterpreterError> {
@@ -147,9 +961,330 @@ impl Interpreter {
 
         for stmt in block.statements.iter() {
             res = self.visit_stmt(stmt)?;
-            if let Object::Return(_) = &res { break; }
+            if res.is_control() { break; }
         }
 
         Ok(res)
     }
+
+    //runs the block in a new scope
+    fn visit_scoped_block(&mut self, block: &BlockStmt) -> Result<Object, InterpreterError> {
+        self.env.push();
+        let res = self.visit_block_stmt(block);
+        self.env.pop();
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn run(inter: &mut Interpreter, code: &str) -> Result<Object, InterpreterError> {
+        inter.feed_next_line(code.to_string(), &mut false);
+        inter.interpret()
+    }
+
+    fn interpreter() -> Interpreter {
+        Interpreter::new(Parser::new(Lexer::new()))
+    }
+
+    //the error the code fails with when run in a new interpreter
+    fn error(code: &str) -> InterpreterError {
+        run(&mut interpreter(), code).expect_err(code)
+    }
+
+    //the value of the code run in a new interpreter, as the repl shows it
+    fn value(code: &str) -> String {
+        match run(&mut interpreter(), code) {
+            Ok(obj) => obj.to_string(),
+            Err(e) => panic!("{code}: {e:?}"),
+        }
+    }
+
+    #[test]
+    fn blocks_have_their_own_scope() {
+        assert_eq!(value("let a: int = 1; { let a: int = 2; }; a"), "1");
+        assert_eq!(value("let a: mut int = 1; { a = a + 1; { a = a * 10; } }; a"), "20");
+        assert_eq!(value("let a: int = 1; { let b: int = a + 1; { let a: int = b * 10; a } }"), "20");
+        //the block is left even when a runtime error stops it
+        let mut inter = interpreter();
+        assert!(run(&mut inter, "let a: int = 1; { let a: int = 0; 1 / a }").is_err());
+        assert_eq!(run(&mut inter, "let a: int = 3; a").unwrap(), Object::Int(3));
+    }
+
+    #[test]
+    fn functions_only_see_globals_and_parameters() {
+        assert_eq!(value("let g: int = 10; fn f(x: int) -> int { let y: int = x + g; y } { let g: int = 1; f(1) }"), "11");
+        assert_eq!(value("fn f(x: int) -> int { x * 2 } let x: int = 5; f(1) + x"), "7");
+    }
+
+    #[test]
+    fn comparisons_and_logic() {
+        assert_eq!(value("1 < 2 && 2 <= 2 && 3 > 2 && 3 >= 4 == false"), "true");
+        assert_eq!(value("1.5 != 1.5 || 2.5 > 1.0"), "true");
+        assert_eq!(value("true == !false && !(1 == 2)"), "true");
+        assert_eq!(value("let a: bool = 1 > 2; a"), "false");
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        assert_eq!(value("let a: int = 0; a != 0 && 10 / a > 1"), "false");
+        assert_eq!(value("let a: int = 0; a == 0 || 10 / a > 1"), "true");
+        assert!(matches!(error("let a: int = 0; a == 0 && 10 / a > 1"), InterpreterError::DivisionByZero(_)));
+    }
+
+    #[test]
+    fn loops() {
+        assert_eq!(value("let a: mut int = 0; let i: mut int = 0; while i < 5 { a = a + i; i = i + 1; } a"), "10");
+        assert_eq!(value("let a: mut int = 0; for i in 1..4 { a = a * 10 + i; } a"), "123");
+        assert_eq!(value("let a: mut int = 0; for i in 3..1 { a = 1; } a"), "0");
+        assert_eq!(value("let a: mut int = 0; loop { a = a + 1; if a == 3 { break } } a"), "3");
+    }
+
+    #[test]
+    fn break_and_continue_target_the_innermost_loop() {
+        assert_eq!(value("let a: mut int = 0; for i in 0..10 { if i % 2 == 0 { continue } if i > 6 { break } a = a + i; } a"), "9");
+        assert_eq!(value("let a: mut int = 0; for i in 0..3 { for j in 0..3 { if j == 1 { break } a = a + 1; } } a"), "3");
+    }
+
+    #[test]
+    fn labels_target_outer_loops() {
+        let code = "let a: mut int = 0;
+            'outer: for i in 0..3 {
+                for j in 0..3 {
+                    if j > i { continue 'outer }
+                    if i == 2 { break 'outer }
+                    a = a * 10 + j;
+                }
+            }
+            a";
+        assert_eq!(value(code), "1");
+        assert_eq!(value("let a: mut int = 0; 'a: loop { while true { a = a + 1; break 'a } } a"), "1");
+    }
+
+    #[test]
+    fn calls() {
+        assert_eq!(value("fn add(a: int, b: int) -> int { a + b } add(2, add(3, 4))"), "9");
+        assert_eq!(value("fn sign(a: int) -> int { if a < 0 { return -1 } if a == 0 { return 0 } 1 } sign(-5) + sign(7) * 10"), "9");
+        assert_eq!(value("let a: mut int = 0; fn bump() { a = a + 1; } bump(); bump(); a"), "2");
+        assert_eq!(value("fn f() { return } f()"), "Null");
+        //a return inside a loop leaves the function
+        assert_eq!(value("fn first(n: int) -> int { for i in 0..n { if i * i > n { return i } } n } first(20)"), "5");
+    }
+
+    #[test]
+    fn recursion() {
+        assert_eq!(value("fn fib(n: int) -> int { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } } fib(15)"), "610");
+        assert_eq!(value("fn fact(n: int) -> int { if n == 0 { return 1 } n * fact(n - 1) } fact(20)"), "2432902008176640000");
+    }
+
+    #[test]
+    fn call_depth_is_limited() {
+        let mut inter = interpreter();
+        inter.set_max_call_depth(10);
+        run(&mut inter, "fn down(n: int) -> int { if n == 0 { 0 } else { down(n - 1) } }").unwrap();
+        assert_eq!(run(&mut inter, "down(9)").unwrap(), Object::Int(0));
+        assert!(matches!(run(&mut inter, "down(10)"), Err(InterpreterError::StackOverflow(_, 10))));
+        //the depth is back to zero after the error
+        assert_eq!(run(&mut inter, "down(9)").unwrap(), Object::Int(0));
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(value(r#"let s: string = "ab" + "cd"; s + s"#), "abcdabcd");
+        assert_eq!(value(r#""abc" < "abd" && "b" > "abc" && "a" == "a" && "a" != "b""#), "true");
+        assert_eq!(value(r#""héllo"[1] + "héllo"[4]"#), "éo");
+        assert_eq!(value(r#"let n: int = 3; "{n} * 2 = {n * 2}, {"ok"}, {n > 2}""#), "3 * 2 = 6, ok, true");
+        assert_eq!(value(r#""\{not code\}""#), "{not code}");
+    }
+
+    #[test]
+    fn runtime_errors() {
+        use InterpreterError::*;
+
+        assert!(matches!(error("1 + 10 / 0"), DivisionByZero(span) if (span.col, span.len) == (5, 6)));
+        assert!(matches!(error("let a: int = 0; 5 % a"), DivisionByZero(_)));
+        assert!(matches!(error("1.5 / 0.0"), DivisionByZero(_)));
+        assert!(matches!(error("9223372036854775807 + 1"), IntegerOverflow(_, IntKind::I64)));
+        assert!(matches!(error("-9223372036854775807 - 2"), IntegerOverflow(_, IntKind::I64)));
+        assert!(matches!(error("3 ** 40"), IntegerOverflow(_, IntKind::I64)));
+        assert!(matches!(error("2 ** -1"), NegativeExponent(_)));
+        assert!(matches!(error("1e308 * 10.0"), NonFiniteFloat(_)));
+        assert!(matches!(error("\"abc\"[3]"), IndexOutOfBounds { index: 3, len: 3, .. }));
+        assert!(matches!(error("\"abc\"[-1]"), IndexOutOfBounds { index: -1, .. }));
+    }
+
+    #[test]
+    fn runtime_errors_stop_the_code() {
+        let mut inter = interpreter();
+        assert!(run(&mut inter, "let a: int = 1 / 0; let b: int = 2").is_err());
+        assert!(inter.vars().is_empty());
+        //the next input runs normally
+        assert_eq!(run(&mut inter, "let a: int = 1; a").unwrap(), Object::Int(1));
+    }
+
+    //the variables defined by the code run so far, as `name = value`
+    fn vars(inter: &Interpreter) -> Vec<String> {
+        inter.vars().into_iter().map(|(name, obj)| format!("{name} = {obj}")).collect()
+    }
+
+    #[test]
+    fn failed_input_has_no_effect() {
+        let mut inter = interpreter();
+        run(&mut inter, "let a: mut int = 1").unwrap();
+        let e = run(&mut inter, "let b: int = 2; a = 3; 1 / 0").unwrap_err();
+        assert!(matches!(e, InterpreterError::DivisionByZero(_)), "{e:?}");
+        assert_eq!(vars(&inter), ["a = 1"]);
+        assert!(run(&mut inter, "b").is_err());
+        run(&mut inter, "let b: string = \"y\"").unwrap();
+        assert_eq!(vars(&inter), ["a = 1", "b = y"]);
+    }
+
+    //the messages of every error found in the code before running it
+    fn check_errors(code: &str) -> Vec<String> {
+        match error(code) {
+            InterpreterError::CheckFailed(errors) => errors.iter().map(|e| e.diagnostics()[0].message.clone()).collect(),
+            e => vec![e.diagnostics()[0].message.clone()],
+        }
+    }
+
+    #[test]
+    fn a_declaration_that_fails_to_parse_is_reported_once() {
+        assert_eq!(check_errors("let a: int = ; a + 1"), ["Unexpected token: expecting an expression, found `;`"]);
+        assert_eq!(check_errors("let a: = 1; let b: int = a * 2; b"), ["Unexpected token: expecting identifier, found `=`"]);
+        //the other errors are still reported, uses before the declaration included
+        assert_eq!(check_errors("a; let a: int = ; 1 + true; c"), [
+            "Cannot find variable `a` in this scope",
+            "Unexpected token: expecting an expression, found `;`",
+            "Cannot apply `+` to `int` and `bool`",
+            "Cannot find variable `c` in this scope",
+        ]);
+    }
+
+    #[test]
+    fn unfinished_input_waits_for_more_lines() {
+        for (first, rest, expected) in [
+            ("fn f(x: int) -> int {", "x * 2 } f(3)", "6"),
+            ("1 +", "2", "3"),
+            ("(1", "+ 2) * 2", "6"),
+            ("\"a", "b\"", "a\nb"),
+            ("let a: int = if true {", "1 } else { 2 }; a", "1"),
+        ] {
+            let mut inter = interpreter();
+            assert!(run(&mut inter, first).is_err_and(|e| e.is_incomplete()), "{first}");
+            inter.feed_next_line(rest.to_string(), &mut true);
+            assert_eq!(inter.interpret().unwrap().to_string(), expected, "{first} {rest}");
+        }
+
+        //input that more lines can't fix is reported at once
+        for code in ["1 + ;", "}", "let a: int = 1 2", "1 + true"] {
+            assert!(!error(code).is_incomplete(), "{code}");
+        }
+    }
+
+    //the type `:type` shows for the code
+    fn type_of(inter: &mut Interpreter, code: &str) -> String {
+        inter.feed_next_line(code.to_string(), &mut false);
+        match inter.type_of() {
+            Ok(t) => t.map_or("!".to_string(), |t| t.to_string()),
+            Err(e) => panic!("{code}: {e:?}"),
+        }
+    }
+
+    #[test]
+    fn types_are_inferred_without_running_the_code() {
+        let mut inter = interpreter();
+        run(&mut inter, "let a: float = 1.5; fn f(x: int) -> bool { x > 0 }").unwrap();
+        assert_eq!(type_of(&mut inter, "a * 2.0"), "float");
+        assert_eq!(type_of(&mut inter, "f(1)"), "bool");
+        assert_eq!(type_of(&mut inter, "let b: int = 1; b as float"), "float");
+        assert_eq!(type_of(&mut inter, "loop { }"), "!");
+        //what the inferred code declares is not kept
+        assert!(run(&mut inter, "b").is_err());
+        assert_eq!(vars(&inter), ["a = 1.5", "f = fn f(int) -> bool"]);
+    }
+
+    #[test]
+    fn reset_forgets_the_definitions_but_not_the_predefined_names() {
+        let mut inter = interpreter();
+        run(&mut inter, "let a: int = 1; fn f() -> int { a }").unwrap();
+        inter.reset();
+        assert!(vars(&inter).is_empty());
+        assert!(run(&mut inter, "f()").is_err());
+        //the names can be declared again
+        assert_eq!(run(&mut inter, "let a: float = PI; sqrt(a * a)").unwrap().to_string(), value("PI"));
+    }
+
+    #[test]
+    fn casts() {
+        for (code, expected) in [
+            ("3 as float", "3.0"),
+            ("let i: int = 3; i as float / 2.0", "1.5"),
+            //floats are truncated toward zero and saturate at the bounds of the type
+            ("2.9 as int", "2"),
+            ("-2.9 as int", "-2"),
+            ("1e30 as int", "9223372036854775807"),
+            ("-1e30 as i8", "-128"),
+            //integers wrap around
+            ("300 as u8", "44"),
+            ("-1 as u8", "255"),
+            ("255u8 as i8", "-1"),
+            ("1.5 as fixed<8>", "1.5"),
+            ("(1.75 as fixed<2>) as int", "1"),
+            ("2.5mm as float", "2.5"),
+            ("1m as float<mm>", "1000mm"),
+            ("-2 as float", "-2.0"),
+        ] {
+            assert_eq!(value(code), expected, "{code}");
+        }
+
+        for code in ["true as int", "\"1\" as int", "1mm as float<s>", "1 as mut float"] {
+            assert!(matches!(error(code), InterpreterError::CheckFailed(_)), "{code}");
+        }
+    }
+
+    #[test]
+    fn int_literals_are_widened_but_int_values_are_not() {
+        assert_eq!(value("let a: float = 1; a"), "1.0");
+        assert_eq!(value("1.5 + 1"), "2.5");
+        assert_eq!(value("fn f(x: float) -> float { x * 2 } f(2)"), "4.0");
+        for code in ["let i: int = 1; i + 1.0", "let i: int = 1; let f: float = i;", "fn f(x: float) -> float { x } let i: int = 1; f(i)"] {
+            assert!(matches!(error(code), InterpreterError::CheckFailed(_)), "{code}");
+        }
+    }
+
+    #[test]
+    fn fixed_point_types_can_be_followed_by_an_equal_sign() {
+        assert_eq!(value("let a: fixed<8>= 1.5; a"), "1.5");
+        assert_eq!(value("let a: fixed < 4 > = 2.25; a"), "2.25");
+        assert_eq!(value("let a: mut fixed<2> = 0.5; a = a + 0.25; a"), "0.75");
+        assert!(matches!(error("let a: fixed<99>= 1.5;"), InterpreterError::CheckFailed(_)));
+    }
+
+    #[test]
+    fn powers_of_0_1_and_minus_1_have_no_limit_on_the_exponent() {
+        assert_eq!(value("0 ** 9223372036854775807"), "0");
+        assert_eq!(value("1 ** 9223372036854775807"), "1");
+        assert_eq!(value("(-1) ** 9223372036854775807"), "-1");
+        assert_eq!(value("(-1) ** 9223372036854775806"), "1");
+        assert_eq!(value("1u64 ** 18446744073709551615u64"), "1");
+        assert!(matches!(error("2 ** 4294967296"), InterpreterError::IntegerOverflow(..)));
+    }
+
+    #[test]
+    fn a_plain_zero_takes_the_unit_it_is_used_with() {
+        assert_eq!(value("let a: float<mm> = 2mm; a > 0.0"), "true");
+        assert_eq!(value("let a: float<m> = -1m; 0 < a"), "false");
+        assert_eq!(value("let a: float<cm> = 2cm; a - 0.0"), "2cm");
+    }
+
+    #[test]
+    fn a_unit_name_after_an_operator_without_spaces_is_part_of_the_unit() {
+        assert_eq!(value("let s: float = 3.0; 10mm * s"), "30mm");
+        assert_eq!(value("let s: float = 3.0; 10mm*s"), "10mm*s");
+        assert_eq!(value("let s: float<s> = 2s; 10mm/s"), "10mm/s");
+        assert_eq!(value("let s: float<s> = 2s; 10mm / s"), "5mm/s");
+    }
 }
@@ -1,52 +1,128 @@
+use std::sync::Arc;
+
+use unicode_xid::UnicodeXID;
+
 use crate::misc::defer;
 use crate::{defer, expr};
-use crate::token::{Token, RESERVED_KEYWORDS};
+use crate::token::{StrPart, Token, RESERVED_KEYWORDS};
 use crate::errors::ParseError;
+use crate::span::{display_width, Span, Spanned};
+use crate::numeric::{IntKind, MAX_FRAC_BITS};
+use crate::units::Unit;
 
 #[derive(Clone)]
 pub struct Lexer {
     text: String,
+    file: Arc<str>,
+    //byte offset of the text in its file, non-zero for fragments
+    base: usize,
     pub pos: usize,
+    line: usize,
+    col: usize,
+}
+
+impl Default for Lexer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Lexer {
     pub fn new() -> Self {
         Self {
             text: "".to_string(),
-            pos: 0
+            file: Arc::from("<stdin>"),
+            base: 0,
+            pos: 0,
+            line: 1,
+            col: 1,
         }
     }
 
-    //give next line to the interpreter to interpret; clones the string - MUST BE ASCII
+    //sets the file name reported in the spans of the following tokens
+    pub fn set_file(&mut self, file: &str) {
+        self.file = Arc::from(file);
+    }
+
+    pub fn source(&self) -> &str {
+        &self.text
+    }
+
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    //give next line to the interpreter to interpret; with append the line continues the text fed before it
     pub fn feed_next_line(&mut self, line: String, append: bool) {
         if append {
+            self.text.push('\n');
             self.text.push_str(&line);
         } else {
             self.text = line;
         }
+        self.base = 0;
         self.pos = 0;
+        self.line = 1;
+        self.col = 1;
     }
 
-    //gets the char at self.pos in the given string. Returns None if self.pos is beyond bounds
-    fn get_current_char(&mut self) -> Option<char> {
-        if self.pos > self.text.len()-1 {
-            return None
-        }
-        
-        Some(self.text.as_bytes()[self.pos] as char)
+    //lexes a piece of source that starts at `span` in its file, such as a string interpolation
+    pub fn feed_fragment(&mut self, text: String, span: &Span) {
+        self.text = text;
+        self.file = span.file.clone();
+        self.base = span.offset;
+        self.pos = 0;
+        self.line = span.line;
+        self.col = span.col;
+    }
+
+    //gets the char starting at byte self.pos. Returns None if self.pos is beyond bounds
+    fn get_current_char(&self) -> Option<char> {
+        self.text.get(self.pos..)?.chars().next()
     }
 
-    //moves self.pos to the next position
+    //moves self.pos to the start of the next char, keeping track of the line and display column
     fn advance(&mut self) {
-        self.pos += 1;
+        let c = match self.get_current_char() {
+            Some(c) => c,
+            None => return
+        };
+
+        if c == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += display_width(c);
+        }
+        self.pos += c.len_utf8();
     }
 
     fn peek(&self) -> Option<char> {
-        if self.pos > self.text.len() - 2 {
-            return None
-        }
+        self.peek_nth(1)
+    }
 
-        Some(self.text.as_bytes()[self.pos + 1] as char)
+    //the char `n` chars after the current one
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.text.get(self.pos..)?.chars().nth(n)
+    }
+
+    //span of the next `len` characters starting at the current position
+    fn span_here(&self, len: usize) -> Span {
+        let chars = self.text[self.pos..].chars().take(len);
+        let (width, byte_len) = chars.fold((0, 0), |(w, b), c| (w + display_width(c), b + c.len_utf8()));
+        Span { offset: self.base + self.pos, byte_len, ..Span::new(self.file.clone(), self.line, self.col, width) }
+    }
+
+    //span from `start` up to the current position; text spanning lines is underlined to the end of the first one
+    fn span_to_here(&self, start: &Span) -> Span {
+        let from = start.offset - self.base;
+        let len = if self.line == start.line {
+            self.col - start.col
+        } else {
+            self.text[from..].lines().next().unwrap_or("").chars().map(display_width).sum()
+        };
+
+        Span { len, byte_len: self.pos - from, ..start.clone() }
     }
 
     //skips the whitespace in the string to the next non-whitespace char
@@ -66,15 +142,25 @@ impl Lexer {
 
         self.advance();
     }
-    
-    fn id(&mut self) -> Result<Token, ParseError> {
+
+    fn id(&mut self) -> Result<Token, Box<ParseError>> {
         let mut res = String::new();
         while let Some(cur_char) = self.get_current_char() {
-            if !cur_char.is_alphanumeric() { break; }
+            if !cur_char.is_xid_continue() { break; }
             res.push(cur_char);
             self.advance();
         }
 
+        //a unit right after `float<` makes the whole type one token, so that `x as float < y` is still a comparison
+        if res == "float" && self.get_current_char() == Some('<') {
+            if let Some(t) = self.quantity_type() { return Ok(t) }
+        }
+
+        //so is `fixed<8>`, so that the `>` of `let a: fixed<8>= 1.5` is not read as part of `>=`
+        if res == "fixed" && self.get_current_char() == Some('<') {
+            if let Some(t) = self.fixed_type()? { return Ok(t) }
+        }
+
         if let Some(t) = RESERVED_KEYWORDS.get(&*res) {
             return Ok(t.clone());
         }
@@ -82,86 +168,530 @@ impl Lexer {
         return Ok(Token::ID(res));
     }
 
-    //iterates over the adjecient digits returning the token with the whole number
-    fn number(&mut self) -> Result<Token, ParseError> {
-        let mut i :String = String::new();
-        let mut float :bool = false;
-        while let Some(cur_char) = self.get_current_char() {
-            if !float && cur_char == '.' {
-                float = true;
-            } else if !cur_char.is_digit(10) {
-                break;
+    //label: QUOTE ID
+    fn label(&mut self) -> Result<Token, Box<ParseError>> {
+        let span = self.span_here(1);
+        self.advance();
+        //a quote not followed by a name, as in `' a`, is not a label
+        if !self.get_current_char().is_some_and(|c| c.is_xid_start() || c == '_') {
+            return Err(Box::new(ParseError::UnknownToken(span, '\'')))
+        }
+        match self.id()? {
+            Token::ID(name) => Ok(Token::Label(name)),
+            _ => Err(Box::new(ParseError::UnknownToken(span, '\'')))
+        }
+    }
+
+    //escape: BACKSLASH (n | t | r | 0 | BACKSLASH | QUOTE | LCURLY | RCURLY | u LCURLY HEX+ RCURLY)
+    fn escape(&mut self) -> Result<char, Box<ParseError>> {
+        let start = self.span_here(2);
+        self.advance();
+        let c = self.get_current_char();
+        self.advance();
+
+        let res = match c {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some(c @ ('\\' | '"' | '{' | '}')) => c,
+            Some('u') => {
+                let mut hex = String::new();
+                if self.get_current_char() == Some('{') {
+                    self.advance();
+                    while let Some(c) = self.get_current_char() {
+                        self.advance();
+                        if c == '}' { break }
+                        hex.push(c);
+                    }
+                }
+
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(c) => c,
+                    None => return Err(Box::new(ParseError::InvalidEscape(self.span_to_here(&start), format!("\\u{{{hex}}}"))))
+                }
+            }
+            Some(c) => return Err(Box::new(ParseError::InvalidEscape(start, format!("\\{c}")))),
+            None => return Err(Box::new(ParseError::UnterminatedString(start))),
+        };
+
+        Ok(res)
+    }
+
+    //the source of an interpolation, up to the matching closing curly brace
+    fn interpolation(&mut self, string_start: &Span) -> Result<StrPart, Box<ParseError>> {
+        self.advance();
+        let start = self.span_here(0);
+        let mut code = String::new();
+        let mut depth = 0;
+
+        loop {
+            match self.get_current_char() {
+                Some('}') if depth == 0 => break,
+                None => return Err(Box::new(ParseError::UnterminatedString(string_start.clone()))),
+                //string literals inside the interpolation are copied as they are
+                Some('"') => {
+                    let nested = self.span_here(1);
+                    code.push('"');
+                    self.advance();
+                    loop {
+                        match self.get_current_char() {
+                            None => return Err(Box::new(ParseError::UnterminatedString(nested))),
+                            Some('"') => break,
+                            Some('\\') => { code.push('\\'); self.advance(); }
+                            Some(_) => (),
+                        }
+                        if let Some(c) = self.get_current_char() { code.push(c); }
+                        self.advance();
+                    }
+                    code.push('"');
+                    self.advance();
+                }
+                Some(c) => {
+                    if c == '{' { depth += 1 }
+                    if c == '}' { depth -= 1 }
+                    code.push(c);
+                    self.advance();
+                }
             }
+        }
+
+        let span = self.span_to_here(&start);
+        self.advance();
+        Ok(StrPart::Code(code, span))
+    }
 
-            i.push(cur_char);
+    //string: QUOTE (char | escape | LCURLY expr RCURLY)* QUOTE
+    fn string(&mut self) -> Result<Token, Box<ParseError>> {
+        let start = self.span_here(1);
+        let mut parts = vec![];
+        let mut lit = String::new();
+        //an invalid escape is reported once the whole string is read, so lexing resumes after it
+        let mut error = None;
+        self.advance();
+
+        loop {
+            match self.get_current_char() {
+                Some('"') => { self.advance(); break },
+                Some('\\') => match self.escape() {
+                    Ok(c) => lit.push(c),
+                    Err(e) => { error.get_or_insert(e); }
+                },
+                Some('{') => {
+                    parts.push(StrPart::Lit(std::mem::take(&mut lit)));
+                    parts.push(self.interpolation(&start)?);
+                }
+                Some(c) => { lit.push(c); self.advance() },
+                None => return Err(Box::new(ParseError::UnterminatedString(start))),
+            }
+        }
+
+        if let Some(e) = error { return Err(e) }
+        if parts.is_empty() { return Ok(Token::Str(lit)) }
+        parts.push(StrPart::Lit(lit));
+        parts.retain(|p| *p != StrPart::Lit(String::new()));
+        Ok(Token::InterpStr(parts))
+    }
+
+    //pushes the adjacent digits of the given radix to `text`, skipping the underscores separating them
+    fn digits(&mut self, text: &mut String, radix: u32) {
+        while let Some(c) = self.get_current_char() {
+            if c != '_' && !c.is_digit(radix) { break }
+            if c != '_' { text.push(c) }
             self.advance();
         }
+    }
 
-        if float {
-            match i.parse::<f64>() {
-                Ok(i) => return Ok(Token::Float(i)),
-                Err(_) => return Err(ParseError::InternalError)
-            };
+    //true if the current char starts the exponent of a float: E (PLUS | MINUS)? DIGIT
+    fn at_exponent(&self) -> bool {
+        match (self.get_current_char(), self.peek(), self.peek_nth(2)) {
+            (Some('e' | 'E'), Some(d), _) if d.is_ascii_digit() => true,
+            (Some('e' | 'E'), Some('+' | '-'), Some(d)) => d.is_ascii_digit(),
+            _ => false,
+        }
+    }
+
+    //number: (ZERO (x | o | b) radix_digits | decimal) suffix?
+    //decimal: DIGIT* (DOT DIGIT*)? (E (PLUS | MINUS)? DIGIT+)?, with a digit before or after the dot;
+    //digits can be separated by underscores
+    fn number(&mut self) -> Result<Token, Box<ParseError>> {
+        let start = self.span_here(0);
+
+        match (self.get_current_char(), self.peek()) {
+            (Some('0'), Some('x' | 'X')) => return self.radix_number(16, "hexadecimal", &start),
+            (Some('0'), Some('o' | 'O')) => return self.radix_number(8, "octal", &start),
+            (Some('0'), Some('b' | 'B')) => return self.radix_number(2, "binary", &start),
+            _ => (),
+        }
+
+        let mut text = String::new();
+        let mut float = false;
+        self.digits(&mut text, 10);
+
+        //a second dot means this is the start of a range, not a decimal point
+        if self.get_current_char() == Some('.') && self.peek() != Some('.') {
+            float = true;
+            text.push('.');
+            self.advance();
+            self.digits(&mut text, 10);
+        }
+
+        if self.at_exponent() {
+            float = true;
+            text.push('e');
+            self.advance();
+            if let Some(sign @ ('+' | '-')) = self.get_current_char() {
+                text.push(sign);
+                self.advance();
+            }
+            self.digits(&mut text, 10);
+        }
+
+        if self.get_current_char().is_some_and(|c| c.is_xid_start()) {
+            if let Some(unit) = self.unit_suffix() {
+                let f = Self::float_value(&text, self.span_to_here(&start))?;
+                return Ok(if unit.is_none() { Token::Float(f) } else { Token::Quantity(f, unit) })
+            }
+            if float { return Err(self.float_suffix()) }
+            return self.number_suffix(&text, 10, &start);
         }
 
-        match i.parse::<i64>() {
+        let span = self.span_to_here(&start);
+        if float { return Ok(Token::Float(Self::float_value(&text, span)?)) }
+
+        //the range of the type the literal is used as is checked by the checker
+        match text.parse::<i128>() {
             Ok(i) => return Ok(Token::Number(i)),
-            Err(_) => return Err(ParseError::InternalError)
+            Err(_) => return Err(Box::new(ParseError::LiteralOutOfRange(span, IntKind::I64)))
         };
     }
 
-    //Lexical Analyzer; breaks the sentence into tokens, returns the next token in the stream
-    pub fn next_token(&mut self) -> Result<Token, ParseError> {
-        use Token::*;
+    fn float_value(text: &str, span: Span) -> Result<f64, Box<ParseError>> {
+        match text.parse::<f64>() {
+            Ok(f) if f.is_finite() => Ok(f),
+            _ => Err(Box::new(ParseError::InvalidNumber(span, "the number is too large for `float`".to_string()))),
+        }
+    }
+
+    //the unit after a number, as in 10mm or 20mm/s; units are ascii, so their length in bytes is the one in chars.
+    //A `*` or `/` written right after the unit followed by the name of a unit always continues it: 10mm*s is ten
+    //millimetre seconds even where `s` is a variable, which is multiplied by 10mm written as `10mm * s`
+    fn unit_suffix(&mut self) -> Option<Unit> {
+        let (unit, len) = Unit::parse_prefix(self.text.get(self.pos..)?, false)?;
+        for _ in 0..len { self.advance(); }
+        Some(unit)
+    }
+
+    //float_type: FLOAT LESS unit GREATER, with the current char on the LESS
+    fn quantity_type(&mut self) -> Option<Token> {
+        let rest = self.text.get(self.pos + 1..)?;
+        let (unit, len) = Unit::parse_prefix(rest, true)?;
+        if !rest[len..].starts_with('>') { return None }
+
+        for _ in 0..len + 2 { self.advance(); }
+        Some(Token::float_type(unit))
+    }
+
+    //fixed_type: FIXED LESS DIGIT+ GREATER, with the current char on the LESS
+    fn fixed_type(&mut self) -> Result<Option<Token>, Box<ParseError>> {
+        let Some(rest) = self.text.get(self.pos + 1..) else { return Ok(None) };
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+        if digits == 0 || !rest[digits..].starts_with('>') { return Ok(None) }
+        let q: i128 = rest[..digits].parse().unwrap_or(i128::MAX);
+
+        self.advance();
+        let span = self.span_here(digits);
+        for _ in 0..digits + 1 { self.advance(); }
+
+        if !(0..=MAX_FRAC_BITS as i128).contains(&q) { return Err(Box::new(ParseError::InvalidFixedBits(span, q))) }
+        Ok(Some(Token::FixedType(q as u8)))
+    }
+
+    //integer literal with a radix prefix, such as 0xff or 0b1010
+    fn radix_number(&mut self, radix: u32, radix_name: &str, start: &Span) -> Result<Token, Box<ParseError>> {
+        let prefix = self.span_here(2);
+        self.advance();
+        self.advance();
+
+        let mut text = String::new();
+        self.digits(&mut text, radix);
+
+        if let Some(c) = self.get_current_char().filter(char::is_ascii_digit) {
+            return Err(Box::new(ParseError::InvalidNumber(self.span_here(1), format!("`{c}` is not a {radix_name} digit"))))
+        }
+        if text.is_empty() {
+            return Err(Box::new(ParseError::InvalidNumber(prefix, format!("no digits after the {radix_name} prefix"))))
+        }
+        if self.get_current_char().is_some_and(|c| c.is_xid_start()) {
+            return self.number_suffix(&text, radix, start);
+        }
 
+        match i128::from_str_radix(&text, radix) {
+            Ok(i) => Ok(Token::Number(i)),
+            Err(_) => Err(Box::new(ParseError::LiteralOutOfRange(self.span_to_here(start), IntKind::I64)))
+        }
+    }
+
+    //the error for a suffix after a float literal; only integers have typed suffixes
+    fn float_suffix(&mut self) -> Box<ParseError> {
+        let start = self.span_here(0);
+        let suffix = match self.id() {
+            Ok(Token::ID(s)) => s,
+            Ok(t) => t.to_string().trim_matches('`').to_string(),
+            Err(e) => return e,
+        };
+        Box::new(ParseError::InvalidSuffix(self.span_to_here(&start), suffix))
+    }
+
+    //the type suffix of an integer literal, as in 255u8; i64 gives a plain int. The range is checked by the checker, once
+    //it knows whether the literal is negated as in -128i8
+    fn number_suffix(&mut self, digits: &str, radix: u32, start: &Span) -> Result<Token, Box<ParseError>> {
+        let suffix_start = self.span_here(0);
+        let suffix = match self.id()? {
+            Token::ID(s) => s,
+            Token::IntType => "i64".to_string(),
+            t => RESERVED_KEYWORDS.iter().find(|(_, k)| **k == t).map_or(String::new(), |(s, _)| s.to_string()),
+        };
+
+        let Some(kind) = IntKind::from_name(&suffix) else {
+            return Err(Box::new(ParseError::InvalidSuffix(self.span_to_here(&suffix_start), suffix)))
+        };
+        match i128::from_str_radix(digits, radix) {
+            Ok(n) if kind == IntKind::I64 => Ok(Token::Number(n)),
+            Ok(n) => Ok(Token::SizedNumber(n, kind)),
+            Err(_) => Err(Box::new(ParseError::LiteralOutOfRange(self.span_to_here(start), kind))),
+        }
+    }
+
+    //skips whitespace and comments up to the start of the next token
+    fn skip_trivia(&mut self) {
         self.skip_whitespace();
-        while let Some(cur_char) = self.get_current_char() {
-            macro_rules! define_token {
-                {$($c:literal => $t:expr),+} => {
-                    match cur_char {
-                        $(
-                            $c => return Ok($t),
-                        )+
-                        _ => (),
-                    }
-                };
+        while let Some('#') = self.get_current_char() {
+            self.skip_comment();
+            self.skip_whitespace();
+        }
+    }
 
-                ($e: expr, $b: expr) => {
-                    if $e { $b };
+    //operators made of two characters; the single character ones that share a prefix are handled by the caller
+    fn double_char_token(&mut self, cur_char: char) -> Option<Token> {
+        use Token::*;
+
+        let token = match (cur_char, self.peek()?) {
+            ('=', '=') => EqualEqual,
+            ('!', '=') => NotEqual,
+            ('<', '=') => LessEqual,
+            ('>', '=') => GreaterEqual,
+            ('&', '&') => And,
+            ('|', '|') => Or,
+            ('.', '.') => DotDot,
+            ('-', '>') => Arrow,
+            ('*', '*') => Power,
+            ('<', '<') => ShiftLeft,
+            ('>', '>') => ShiftRight,
+            _ => return None
+        };
+
+        self.advance();
+        self.advance();
+        Some(token)
+    }
+
+    fn token(&mut self) -> Result<Token, Box<ParseError>> {
+        use Token::*;
+
+        let cur_char = match self.get_current_char() {
+            Some(c) => c,
+            None => return Ok(EOF)
+        };
+
+        macro_rules! define_token {
+            {$($c:literal => $t:expr),+} => {
+                match cur_char {
+                    $(
+                        $c => return Ok($t),
+                    )+
+                    _ => (),
                 }
-            }
+            };
 
-            if cur_char == '#' {
-                self.skip_comment();
-                self.skip_whitespace();
-                continue;
+            ($e: expr, $b: expr) => {
+                if $e { $b };
             }
+        }
 
-            let pos = self.pos;
-
-            define_token!(cur_char.is_digit(10), return self.number());
-            define_token!(cur_char.is_alphanumeric(), return self.id());
-
-            defer!(self.advance());
-            define_token!{
-                '+' => Plus,
-                '-' => Minus,
-                '*' => Multiply,
-                '/' => Divide,
-                '(' => LeftParen,
-                ')' => RightParen,
-                '{' => LeftCurly,
-                '}' => RightCurly,
-                '=' => Equal,
-                ';' => Semicolon,
-                ':' => Colon,
-                ',' => Comma
-            }
+        let span = self.span_here(1);
+
+        define_token!(cur_char.is_ascii_digit(), return self.number());
+        define_token!(cur_char == '.' && self.peek().is_some_and(|c| c.is_ascii_digit()), return self.number());
+        define_token!(cur_char.is_xid_start() || cur_char == '_', return self.id());
+        define_token!(cur_char == '\'', return self.label());
+        define_token!(cur_char == '"', return self.string());
+        if let Some(t) = self.double_char_token(cur_char) { return Ok(t) }
+
+        defer!(self.advance());
+        define_token!{
+            '+' => Plus,
+            '-' => Minus,
+            '*' => Multiply,
+            '/' => Divide,
+            '%' => Modulo,
+            '&' => BitAnd,
+            '|' => BitOr,
+            '^' => BitXor,
+            '~' => BitNot,
+            '(' => LeftParen,
+            ')' => RightParen,
+            '{' => LeftCurly,
+            '}' => RightCurly,
+            '[' => LeftBracket,
+            ']' => RightBracket,
+            '=' => Equal,
+            '<' => Less,
+            '>' => Greater,
+            '!' => Not,
+            ';' => Semicolon,
+            ':' => Colon,
+            ',' => Comma,
+            '.' => Dot
+        }
+
+        return Err(Box::new(ParseError::UnknownToken(span, cur_char)));
+    }
+
+    //Lexical Analyzer; breaks the sentence into tokens, returns the next token in the stream along with its location
+    pub fn next_token(&mut self) -> Result<Spanned<Token>, Box<ParseError>> {
+        self.skip_trivia();
+        let start = self.span_here(0);
+        let token = self.token()?;
+
+        if token == Token::EOF { return Ok(Spanned::new(token, Span { len: 1, ..start })) }
+        Ok(Spanned::new(token, self.span_to_here(&start)))
+    }
+
+}
 
-            return Err(ParseError::UnknownToken(pos, cur_char));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::BaseUnit;
+
+    fn lexer(code: &str) -> Lexer {
+        let mut lexer = Lexer::new();
+        lexer.feed_next_line(code.to_string(), false);
+        lexer
+    }
+
+    fn error(code: &str) -> ParseError {
+        *lexer(code).next_token().unwrap_err()
+    }
+
+    //every token of the code with where it is: line, column, display width, byte offset and byte length
+    fn tokens(code: &str) -> Vec<(Token, [usize; 5])> {
+        let mut lexer = lexer(code);
+        let mut res = vec![];
+        loop {
+            let Spanned { node, span } = lexer.next_token().unwrap();
+            if node == Token::EOF { return res }
+            res.push((node, [span.line, span.col, span.len, span.offset, span.byte_len]));
         }
+    }
 
-        return Ok(Token::EOF);
+    #[test]
+    fn unicode_identifiers_and_columns() {
+        let id = |s: &str| Token::ID(s.to_string());
+        assert_eq!(tokens("größe + 変数"), [
+            (id("größe"), [1, 1, 5, 0, 7]),
+            (Token::Plus, [1, 7, 1, 8, 1]),
+            (id("変数"), [1, 9, 4, 10, 6]),
+        ]);
+        //columns after wide chars and tabs are display columns, offsets are in bytes
+        assert_eq!(tokens("\"日本\"\tx\n  é"), [
+            (Token::Str("日本".to_string()), [1, 1, 6, 0, 8]),
+            (id("x"), [1, 11, 1, 9, 1]),
+            (id("é"), [2, 3, 1, 13, 2]),
+        ]);
+        assert_eq!(tokens("_a1"), [(id("_a1"), [1, 1, 3, 0, 3])]);
     }
 
+    #[test]
+    fn unknown_chars_are_reported_whole() {
+        assert!(matches!(error("€"), ParseError::UnknownToken(span, '€') if (span.len, span.byte_len) == (1, 3)));
+        assert!(matches!(error("🦀"), ParseError::UnknownToken(span, '🦀') if (span.len, span.byte_len) == (2, 4)));
+    }
+
+    #[test]
+    fn fixed_point_types_are_one_token() {
+        let kinds = |code: &str| tokens(code).into_iter().map(|(t, _)| t).collect::<Vec<_>>();
+        assert_eq!(tokens("fixed<8>= 1"), [(Token::FixedType(8), [1, 1, 8, 0, 8]), (Token::Equal, [1, 9, 1, 8, 1]), (Token::Number(1), [1, 11, 1, 10, 1])]);
+        assert_eq!(kinds("fixed<16>>x"), [Token::FixedType(16), Token::Greater, Token::ID("x".to_string())]);
+        //written with spaces the type is left to the parser
+        assert_eq!(kinds("fixed < 8 >"), [Token::Fixed, Token::Less, Token::Number(8), Token::Greater]);
+        assert!(matches!(error("fixed<99>"), ParseError::InvalidFixedBits(span, 99) if (span.col, span.len) == (7, 2)));
+    }
+
+    #[test]
+    fn quote_without_a_name_is_not_a_label() {
+        assert_eq!(lexer("'outer: loop").next_token().unwrap().node, Token::Label("outer".to_string()));
+        assert!(matches!(error("'"), ParseError::UnknownToken(_, '\'')));
+        assert!(matches!(error("' a"), ParseError::UnknownToken(_, '\'')));
+    }
+
+    #[test]
+    fn string_escapes() {
+        let token = |code: &str| lexer(code).next_token().unwrap().node;
+        assert_eq!(token(r#""a\tb\n\"c\" \\ \{ \u{e9}""#), Token::Str("a\tb\n\"c\" \\ { é".to_string()));
+        assert_eq!(token(r#""""#), Token::Str(String::new()));
+        assert!(matches!(error(r#""a\qb""#), ParseError::InvalidEscape(span, e) if e == "\\q" && span.col == 3));
+        assert!(matches!(error(r#""\u{110000}""#), ParseError::InvalidEscape(..)));
+        assert!(matches!(error("\"abc"), ParseError::UnterminatedString(span) if span.col == 1));
+    }
+
+    #[test]
+    fn interpolated_strings_keep_the_code() {
+        let token = |code: &str| lexer(code).next_token().unwrap().node;
+        let Token::InterpStr(parts) = token(r#""x = {x + 1}, {"}"}!""#) else { panic!("not interpolated") };
+        let parts: Vec<String> = parts.iter().map(|p| match p {
+            StrPart::Lit(s) => format!("lit {s}"),
+            StrPart::Code(code, span) => format!("code {code} at {}", span.col),
+        }).collect();
+        assert_eq!(parts, ["lit x = ", "code x + 1 at 7", "lit , ", "code \"}\" at 16", "lit !"]);
+    }
+
+    #[test]
+    fn number_literals() {
+        let token = |code: &str| lexer(code).next_token().unwrap().node;
+        assert_eq!(token("1_000"), Token::Number(1000));
+        assert_eq!(token("0xff"), Token::Number(255));
+        assert_eq!(token("0b1010"), Token::Number(10));
+        assert_eq!(token("9223372036854775808"), Token::Number(1 << 63));
+        assert_eq!(token("255u8"), Token::SizedNumber(255, IntKind::U8));
+        assert_eq!(token("0x80i8"), Token::SizedNumber(128, IntKind::I8));
+        assert_eq!(token("7i64"), Token::Number(7));
+        assert_eq!(token("1.5"), Token::Float(1.5));
+        assert_eq!(token("2e3"), Token::Float(2000.0));
+        assert_eq!(token("10mm"), Token::Quantity(10.0, Unit::base(BaseUnit::Mm)));
+    }
+
+    #[test]
+    fn units_are_chained_by_operators_without_spaces() {
+        let mm = Unit::base(BaseUnit::Mm);
+        let s = Unit::base(BaseUnit::S);
+        let kinds = |code: &str| tokens(code).into_iter().map(|(t, _)| t).collect::<Vec<_>>();
+        assert_eq!(kinds("10mm*s"), [Token::Quantity(10.0, mm.mul(&s))]);
+        assert_eq!(kinds("10mm/s^2"), [Token::Quantity(10.0, mm.div(&s).div(&s))]);
+        //with a space, or with a name that is not a unit, the operator is left for the parser
+        assert_eq!(kinds("10mm * s"), [Token::Quantity(10.0, mm.clone()), Token::Multiply, Token::ID("s".to_string())]);
+        assert_eq!(kinds("10mm *s"), [Token::Quantity(10.0, mm.clone()), Token::Multiply, Token::ID("s".to_string())]);
+        assert_eq!(kinds("10mm/speed"), [Token::Quantity(10.0, mm), Token::Divide, Token::ID("speed".to_string())]);
+    }
+
+    #[test]
+    fn invalid_number_literals() {
+        assert!(matches!(error("1.5u8"), ParseError::InvalidSuffix(_, s) if s == "u8"));
+        assert!(matches!(error("3kg"), ParseError::InvalidSuffix(_, s) if s == "kg"));
+        assert!(matches!(error("0x"), ParseError::InvalidNumber(..)));
+        assert!(matches!(error("0b102"), ParseError::InvalidNumber(..)));
+        assert!(matches!(error("1e400"), ParseError::InvalidNumber(..)));
+        assert!(matches!(error("340282366920938463463374607431768211456"), ParseError::LiteralOutOfRange(_, IntKind::I64)));
+    }
 }
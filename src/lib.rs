@@ -0,0 +1,24 @@
+#![allow(clippy::needless_return, clippy::module_inception)]
+
+//the arm scripting language; `engine::Engine` runs scripts from Rust, the other modules are its building blocks
+
+pub mod interpreter;
+pub mod parser;
+pub mod lexer;
+pub mod token;
+pub mod ast;
+pub mod errors;
+pub mod misc;
+pub mod symbols;
+pub mod diagnostics;
+pub mod environment;
+pub mod object;
+pub mod span;
+pub mod numeric;
+pub mod units;
+pub mod geometry;
+pub mod engine;
+pub mod stdlib;
+
+pub use engine::{Engine, Error, ErrorKind, Value, ValueType};
+pub use geometry::{Pose, Quat, Vec3};
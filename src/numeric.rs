@@ -0,0 +1,183 @@
+//the fixed width integer and fixed-point types, and the conversions between numeric types
+
+//width and signedness of an integer type. `int` is I64; the others are the sized types written i8, u16 and so on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IntKind {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+//the sized integer types, as they are written in the code
+pub const SIZED_INTS: &[(&str, IntKind)] = &[
+    ("i8", IntKind::I8),
+    ("i16", IntKind::I16),
+    ("i32", IntKind::I32),
+    ("u8", IntKind::U8),
+    ("u16", IntKind::U16),
+    ("u32", IntKind::U32),
+    ("u64", IntKind::U64),
+];
+
+//fixed-point values are stored in 64 bits, of which at most this many are fractional
+pub const MAX_FRAC_BITS: u8 = 62;
+
+impl IntKind {
+    pub fn from_name(name: &str) -> Option<IntKind> {
+        if name == "i64" { return Some(IntKind::I64) }
+        SIZED_INTS.iter().find(|(n, _)| *n == name).map(|(_, k)| *k)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            IntKind::I64 => "int",
+            k => SIZED_INTS.iter().find(|(_, kind)| kind == k).map(|(n, _)| *n).unwrap_or("?"),
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        use IntKind::*;
+
+        match self {
+            I8 | U8 => 8,
+            I16 | U16 => 16,
+            I32 | U32 => 32,
+            I64 | U64 => 64,
+        }
+    }
+
+    pub fn signed(&self) -> bool {
+        matches!(self, IntKind::I8 | IntKind::I16 | IntKind::I32 | IntKind::I64)
+    }
+
+    pub fn min(&self) -> i128 {
+        if self.signed() { -(1 << (self.bits() - 1)) } else { 0 }
+    }
+
+    pub fn max(&self) -> i128 {
+        if self.signed() { (1 << (self.bits() - 1)) - 1 } else { (1 << self.bits()) - 1 }
+    }
+
+    pub fn contains(&self, v: i128) -> bool {
+        self.min() <= v && v <= self.max()
+    }
+
+    //keeps the low bits of `v`, like converting between integer types with `as` in Rust
+    pub fn wrap(&self, v: i128) -> i128 {
+        let modulus = 1i128 << self.bits();
+        let v = v.rem_euclid(modulus);
+        if v > self.max() { v - modulus } else { v }
+    }
+
+    pub fn saturate(&self, v: i128) -> i128 {
+        v.clamp(self.min(), self.max())
+    }
+}
+
+//value of a fixed-point number with `q` fractional bits
+pub fn fixed_to_f64(raw: i64, q: u8) -> f64 {
+    raw as f64 / (1u64 << q) as f64
+}
+
+//fixed-point number with `q` fractional bits closest to `f`, saturating at the bounds of the type
+pub fn fixed_from_f64(f: f64, q: u8) -> i64 {
+    (f * (1u64 << q) as f64).round() as i64
+}
+
+//fixed-point number with `q` fractional bits holding the integer `i`, saturating at the bounds of the type
+pub fn fixed_from_int(i: i128, q: u8) -> i64 {
+    let raw = i.saturating_mul(1 << q);
+    raw.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+//converts between numbers of fractional bits, rounding to the nearest representable value
+pub fn fixed_rescale(raw: i64, from: u8, to: u8) -> i64 {
+    if to >= from { return fixed_from_int(raw as i128, to - from) }
+
+    let shift = from - to;
+    ((raw as i128 + (1 << (shift - 1))) >> shift) as i64
+}
+
+//`base` to the power of the non-negative `exp`, None on overflow; the exponents too large for checked_pow only leave
+//a result for the bases 0, 1 and -1
+pub fn checked_pow(base: i128, exp: i128) -> Option<i128> {
+    match u32::try_from(exp) {
+        Ok(exp) => base.checked_pow(exp),
+        Err(_) => match base {
+            0 | 1 => Some(base),
+            -1 => Some(if exp % 2 == 0 { 1 } else { -1 }),
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn int_kind_ranges() {
+        assert_eq!((IntKind::I8.min(), IntKind::I8.max()), (-128, 127));
+        assert_eq!((IntKind::U64.min(), IntKind::U64.max()), (0, u64::MAX as i128));
+        assert_eq!((IntKind::I64.min(), IntKind::I64.max()), (i64::MIN as i128, i64::MAX as i128));
+        assert!(IntKind::U16.contains(65535) && !IntKind::U16.contains(65536) && !IntKind::U16.contains(-1));
+    }
+
+    #[test]
+    fn wrap_keeps_the_low_bits() {
+        assert_eq!(IntKind::U8.wrap(300), 44);
+        assert_eq!(IntKind::U8.wrap(-1), 255);
+        assert_eq!(IntKind::I8.wrap(128), -128);
+        assert_eq!(IntKind::I8.wrap(-129), 127);
+        assert_eq!(IntKind::U64.wrap(-1), u64::MAX as i128);
+        assert_eq!(IntKind::I16.saturate(40000), 32767);
+        assert_eq!(IntKind::U32.saturate(-5), 0);
+    }
+
+    #[test]
+    fn fixed_from_f64_rounds_to_nearest() {
+        assert_eq!(fixed_from_f64(1.5, 4), 24);
+        //ties are rounded away from zero
+        assert_eq!(fixed_from_f64(0.375, 2), 2);
+        assert_eq!(fixed_from_f64(-0.375, 2), -2);
+        assert_eq!(fixed_from_f64(0.1, 8), 26);
+        assert_eq!(fixed_from_f64(1e30, 8), i64::MAX);
+        assert_eq!(fixed_to_f64(fixed_from_f64(-2.25, 16), 16), -2.25);
+    }
+
+    #[test]
+    fn fixed_from_int_saturates() {
+        assert_eq!(fixed_from_int(3, 4), 48);
+        assert_eq!(fixed_from_int(-3, 4), -48);
+        assert_eq!(fixed_from_int(i64::MAX as i128, 4), i64::MAX);
+        assert_eq!(fixed_from_int(i64::MIN as i128, 1), i64::MIN);
+    }
+
+    #[test]
+    fn fixed_rescale_rounds_to_nearest() {
+        assert_eq!(fixed_rescale(3, 2, 4), 12);
+        assert_eq!(fixed_rescale(5, 2, 1), 3);
+        assert_eq!(fixed_rescale(6, 3, 1), 2);
+        //ties are rounded up
+        assert_eq!(fixed_rescale(3, 2, 1), 2);
+        assert_eq!(fixed_rescale(-3, 2, 1), -1);
+        assert_eq!(fixed_rescale(i64::MAX, 0, 1), i64::MAX);
+    }
+
+    #[test]
+    fn powers_with_huge_exponents() {
+        let huge = u64::MAX as i128;
+        assert_eq!(checked_pow(3, 4), Some(81));
+        assert_eq!(checked_pow(2, 127), None);
+        assert_eq!(checked_pow(0, huge), Some(0));
+        assert_eq!(checked_pow(1, huge), Some(1));
+        assert_eq!(checked_pow(-1, huge), Some(-1));
+        assert_eq!(checked_pow(-1, huge - 1), Some(1));
+        assert_eq!(checked_pow(2, huge), None);
+    }
+}
@@ -1,23 +1,189 @@
 use std::fmt::{ Display, Formatter };
+use std::rc::Rc;
+
+use crate::ast::ast::{FnDecl, Type};
+use crate::symbols::FnSignature;
+use crate::token::Token;
+use crate::numeric::{fixed_to_f64, IntKind};
+use crate::units::Unit;
+use crate::geometry::{Pose, Quat, Vec3};
+
+pub type NativeFnPtr = Rc<dyn Fn(&[Object]) -> Result<Object, String>>;
+
+//function implemented in Rust by the host; an error message returned by it becomes a runtime error at the call.
+//Overloaded natives have several signatures and tell them apart by the types of the arguments they get
+#[derive(Clone)]
+pub struct NativeFn {
+    pub name: String,
+    pub signatures: Vec<FnSignature>,
+    pub func: NativeFnPtr,
+}
+
+impl NativeFn {
+    pub fn new(name: &str, params: &[Token], ret: Token, func: impl Fn(&[Object]) -> Result<Object, String> + 'static) -> Self {
+        Self { name: name.to_string(), signatures: vec![Self::signature(params, ret)], func: Rc::new(func) }
+    }
+
+    fn signature(params: &[Token], ret: Token) -> FnSignature {
+        let params = params.iter().map(|t| Type::new(t.clone(), false)).collect();
+        FnSignature { params, variadic: None, ret: Type::new(ret, false) }
+    }
+
+    //any number of arguments of type `t` can follow the parameters of the last signature
+    pub fn variadic(mut self, t: Token) -> Self {
+        if let Some(last) = self.signatures.last_mut() {
+            last.variadic = Some(Type::new(t, false));
+        }
+        self
+    }
+
+    //adds another signature the function can be called with
+    pub fn overload(mut self, params: &[Token], ret: Token) -> Self {
+        self.signatures.push(Self::signature(params, ret));
+        self
+    }
+
+    //the signature accepting arguments of these types
+    pub fn signature_for(&self, args: &[Object]) -> Option<&FnSignature> {
+        let types: Option<Vec<Token>> = args.iter().map(Object::type_token).collect();
+        let types = types?;
+        self.signatures.iter().find(|s| s.accepts(&types))
+    }
+}
+
+impl std::fmt::Debug for NativeFn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "NativeFn({})", self.name)
+    }
+}
+
+//natives are equal if they are the same registration
+impl PartialEq for NativeFn {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.func, &other.func)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Object {
     Int(i64),
+    //value of a sized integer type, always within the range of the type
+    SizedInt(i128, IntKind),
     Float(f64),
+    //raw value and number of fractional bits of a fixed-point number
+    Fixed(i64, u8),
+    //float with a unit, never the empty one
+    Quantity(f64, Unit),
+    Vec3(Vec3),
+    Quat(Quat),
+    Pose(Pose),
+    Bool(bool),
     Str(String),
+    Fn(Rc<FnDecl>),
+    Native(Rc<NativeFn>),
     Return(Box<Object>),
+    //loop control signals, carrying the label of the targeted loop
+    Break(Option<String>),
+    Continue(Option<String>),
     Null,
 }
 
+impl Object {
+    //true for the values that stop the execution of the enclosing blocks: return, break and continue
+    pub fn is_control(&self) -> bool {
+        matches!(self, Object::Return(_) | Object::Break(_) | Object::Continue(_))
+    }
+
+    //token of the type a variable needs to hold this value; None for values that can't be stored in variables
+    pub fn type_token(&self) -> Option<Token> {
+        match self {
+            Object::Int(_) => Some(Token::IntType),
+            Object::SizedInt(_, kind) => Some(Token::SizedIntType(*kind)),
+            Object::Float(_) => Some(Token::FloatType),
+            Object::Fixed(_, q) => Some(Token::FixedType(*q)),
+            Object::Quantity(_, unit) => Some(Token::QuantityType(unit.clone())),
+            Object::Vec3(_) => Some(Token::Vec3Type),
+            Object::Quat(_) => Some(Token::QuatType),
+            Object::Pose(_) => Some(Token::PoseType),
+            Object::Bool(_) => Some(Token::BoolType),
+            Object::Str(_) => Some(Token::StrType),
+            _ => None,
+        }
+    }
+
+    //name of the language type of the value, used in error messages
+    pub fn type_name(&self) -> String {
+        use Object::*;
+
+        let name = match self {
+            Int(_) => "int",
+            SizedInt(_, kind) => kind.name(),
+            Float(_) => "float",
+            Fixed(_, q) => return format!("fixed<{q}>"),
+            Quantity(_, unit) => return format!("float<{unit}>"),
+            Vec3(_) => "vec3",
+            Quat(_) => "quat",
+            Pose(_) => "pose",
+            Bool(_) => "bool",
+            Str(_) => "string",
+            Fn(_) | Native(_) => "fn",
+            Return(obj) => return obj.type_name(),
+            Break(_) | Continue(_) => "null",
+            Null => "null",
+        };
+        name.to_string()
+    }
+
+    //value and type of an int or sized integer
+    pub fn as_integer(&self) -> Option<(i128, IntKind)> {
+        match self {
+            Object::Int(i) => Some((*i as i128, IntKind::I64)),
+            Object::SizedInt(i, kind) => Some((*i, *kind)),
+            _ => None,
+        }
+    }
+
+    //integer of the given type; `v` must be in its range
+    pub fn integer(v: i128, kind: IntKind) -> Object {
+        match kind {
+            IntKind::I64 => Object::Int(v as i64),
+            kind => Object::SizedInt(v, kind),
+        }
+    }
+
+    //float with the given unit, a plain float for the empty one
+    pub fn quantity(f: f64, unit: Unit) -> Object {
+        if unit.is_none() { Object::Float(f) } else { Object::Quantity(f, unit) }
+    }
+}
+
 impl Display for Object {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
         use Object::*;
 
         match self {
             Int(i) => write!(f, "{i}"),
-            Float(fl) => write!(f, "{fl}"),
+            SizedInt(i, _) => write!(f, "{i}"),
+            //floats always show a decimal point or an exponent, so that they read as floats
+            Float(fl) => write!(f, "{fl:?}"),
+            Fixed(raw, q) => write!(f, "{}", fixed_to_f64(*raw, *q)),
+            Quantity(fl, unit) => write!(f, "{fl}{}", unit.suffix()),
+            Vec3(v) => write!(f, "{v}"),
+            Quat(q) => write!(f, "{q}"),
+            Pose(p) => write!(f, "{p}"),
+            Bool(b) => write!(f, "{b}"),
             Str(s) => write!(f, "{s}"),
-            Return(obj) => write!(f, "{}", obj.to_string()),
+            Fn(decl) => {
+                let params: Vec<String> = decl.params.iter().map(|p| p.t.to_string()).collect();
+                write!(f, "fn {}({}) -> {}", decl.name, params.join(", "), decl.ret)
+            }
+            Native(native) => {
+                let signatures: Vec<String> = native.signatures.iter().map(|s| format!("fn {}{s}", native.name)).collect();
+                write!(f, "{}", signatures.join(" | "))
+            }
+            Return(obj) => write!(f, "{obj}"),
+            Break(_) => write!(f, "break"),
+            Continue(_) => write!(f, "continue"),
             Null => write!(f, "Null")
         }
     }
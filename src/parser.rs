@@ -1,11 +1,86 @@
-use crate::ast::ast::{BlockStmt, Expr, Stmt, Type};
-use crate::token::Token;
+use std::rc::Rc;
+
+use crate::ast::ast::{BlockStmt, Expr, ExprKind, FnDecl, Param, Stmt, StmtKind, Type};
+use crate::token::{StrPart, Token};
 use crate::errors::ParseError;
 use crate::lexer::Lexer;
+use crate::span::{Span, Spanned};
+use crate::numeric::MAX_FRAC_BITS;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Assoc {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Fixity {
+    Prefix,
+    Infix(Assoc),
+    Postfix,
+}
+
+//entry of the operator table; an operator with a higher binding power binds tighter
+struct Operator {
+    token: Token,
+    fixity: Fixity,
+    bp: u8,
+}
+
+macro_rules! op {
+    ($t:ident, $fixity:expr, $bp:literal) => {
+        Operator { token: Token::$t, fixity: $fixity, bp: $bp }
+    };
+}
+
+//every operator the expression parser knows about; adding an operator only needs an entry here
+static OPERATORS: &[Operator] = {
+    use Assoc::*;
+    use Fixity::*;
+
+    &[
+        op!(Or, Infix(Left), 1),
+        op!(And, Infix(Left), 2),
+        op!(EqualEqual, Infix(Left), 3),
+        op!(NotEqual, Infix(Left), 3),
+        op!(Less, Infix(Left), 4),
+        op!(LessEqual, Infix(Left), 4),
+        op!(Greater, Infix(Left), 4),
+        op!(GreaterEqual, Infix(Left), 4),
+        op!(BitOr, Infix(Left), 5),
+        op!(BitXor, Infix(Left), 6),
+        op!(BitAnd, Infix(Left), 7),
+        op!(ShiftLeft, Infix(Left), 8),
+        op!(ShiftRight, Infix(Left), 8),
+        op!(Plus, Infix(Left), 9),
+        op!(Minus, Infix(Left), 9),
+        op!(Multiply, Infix(Left), 10),
+        op!(Divide, Infix(Left), 10),
+        op!(Modulo, Infix(Left), 10),
+        //looser than the prefix operators so -x as float is (-x) as float
+        op!(As, Postfix, 11),
+        op!(Minus, Prefix, 12),
+        op!(Not, Prefix, 12),
+        op!(BitNot, Prefix, 12),
+        //binds tighter than the prefix operators so -2 ** 2 is -(2 ** 2)
+        op!(Power, Infix(Right), 13),
+        op!(LeftParen, Postfix, 14),
+        op!(LeftBracket, Postfix, 14),
+        op!(Dot, Postfix, 14),
+    ]
+};
 
 pub struct Parser {
     lexer: Lexer,
-    current_token: Token
+    current_token: Token,
+    current_span: Span,
+    prev_span: Span,
+    //errors recovered from since the last call to parse
+    errors: Vec<ParseError>,
+    //the `{`, `(` and `[` not closed yet, innermost last
+    open: Vec<Spanned<Token>>,
+    //names of the variables whose declarations failed to parse since the last call to parse, with their spans
+    failed_decls: Vec<(String, Span)>,
 }
 
 impl Parser {
@@ -14,80 +89,325 @@ impl Parser {
         Self {
             lexer,
             current_token: Token::EOF,
+            current_span: Span::default(),
+            prev_span: Span::default(),
+            errors: vec![],
+            open: vec![],
+            failed_decls: vec![],
         }
     }
 
+    pub fn set_file(&mut self, file: &str) {
+        self.lexer.set_file(file);
+    }
+
+    pub fn source(&self) -> &str {
+        self.lexer.source()
+    }
+
+    pub fn file(&self) -> &str {
+        self.lexer.file()
+    }
+
+    //an error in the first token is kept and reported by the next call to parse
     pub fn feed_next_line(&mut self, text: String, append: bool) {
         self.lexer.feed_next_line(text, append);
-        self.current_token = self.lexer.next_token().unwrap();
+        self.read_first_token();
+    }
+
+    fn read_first_token(&mut self) {
+        self.errors.clear();
+        self.open.clear();
+        self.bump();
+        self.prev_span = self.current_span.clone();
     }
-    
 
-    fn eat(&mut self, expected_token: Token) -> Result<(), ParseError> {
+    //moves to the next token; lexer errors are recorded and the text they were found in is skipped
+    fn bump(&mut self) {
+        loop {
+            match self.lexer.next_token() {
+                Ok(token) => {
+                    self.current_token = token.node;
+                    self.prev_span = std::mem::replace(&mut self.current_span, token.span);
+                    return
+                }
+                Err(e) => self.errors.push(*e),
+            }
+        }
+    }
+
+    //skips tokens up to and including the next `;`, or up to the `}` closing the current block, to resume parsing after an error
+    fn synchronize(&mut self) {
+        let mut depth = 0;
+        loop {
+            match self.current_token {
+                Token::Semicolon if depth == 0 => { self.bump(); return }
+                Token::RightCurly if depth == 0 => return,
+                Token::EOF => return,
+                Token::LeftCurly => depth += 1,
+                Token::RightCurly => depth -= 1,
+                _ => (),
+            }
+            self.bump();
+        }
+    }
+
+
+    fn eat(&mut self, expected_token: Token) -> Result<(), Box<ParseError>> {
         if self.current_token == Token::EOF && expected_token != Token::EOF {
-            return Err(ParseError::WaitForInput)
+            return Err(self.end_of_input())
         }
 
         if self.current_token != expected_token {
-            return Err(ParseError::WrongToken(self.lexer.pos, expected_token, self.current_token.clone()))
+            return Err(Box::new(ParseError::WrongToken(self.current_span.clone(), expected_token, self.current_token.clone())))
+        }
+
+        match expected_token {
+            Token::LeftCurly | Token::LeftParen | Token::LeftBracket => self.open.push(Spanned::new(expected_token, self.current_span.clone())),
+            Token::RightCurly | Token::RightParen | Token::RightBracket => { self.open.pop(); }
+            _ => (),
         }
 
-        self.current_token = self.lexer.next_token()?;
+        let token = self.lexer.next_token()?;
+        self.current_token = token.node;
+        self.prev_span = std::mem::replace(&mut self.current_span, token.span);
         Ok(())
     }
 
+    fn end_of_input(&self) -> Box<ParseError> {
+        Box::new(ParseError::WaitForInput(self.current_span.clone(), self.open.last().cloned()))
+    }
+
+    //span from `start` to the end of the last eaten token
+    fn span_from(&self, start: &Span) -> Span {
+        start.to(&self.prev_span)
+    }
+
 
-    fn scope_statement(&mut self) -> Result<BlockStmt, ParseError> {
+    fn scope_statement(&mut self) -> Result<BlockStmt, Box<ParseError>> {
+        let start = self.current_span.clone();
         self.eat(Token::LeftCurly)?;
-        let nodes = self.statement_block()?;
+        let mut nodes = self.statement_block()?;
         self.eat(Token::RightCurly)?;
 
+        nodes.span = self.span_from(&start);
         Ok(nodes)
     }
 
-    fn statement_block(&mut self) -> Result<BlockStmt, ParseError> {
-        let node = self.statement()?;
-        let mut res :Vec<Stmt> = vec![node];
+    //records the error and skips to the next statement; running out of input is not recovered from
+    fn recover(&mut self, e: Box<ParseError>) -> Result<(), Box<ParseError>> {
+        if let ParseError::WaitForInput(..) = *e { return Err(e) }
+        self.errors.push(*e);
+        self.synchronize();
+        Ok(())
+    }
 
-        while self.current_token == Token::Semicolon {
-            self.eat(Token::Semicolon)?;
-            res.push(self.statement()?);
+    //statements that fail to parse are left out of the block
+    fn statement_block(&mut self) -> Result<BlockStmt, Box<ParseError>> {
+        let start = self.current_span.clone();
+        let mut res :Vec<Stmt> = vec![];
+
+        loop {
+            let open = self.open.len();
+            match self.statement() {
+                Ok(node) => res.push(node),
+                Err(e) => {
+                    //the delimiters opened by the statement are skipped along with it
+                    self.open.truncate(open);
+                    self.recover(e)?;
+                    if self.current_token.is(&[Token::RightCurly, Token::EOF]) { break }
+                    continue;
+                }
+            }
+
+            //statements ending with a block don't need a semicolon after them
+            if self.current_token == Token::Semicolon {
+                self.eat(Token::Semicolon)?;
+                continue;
+            }
+            if res.last().is_some_and(Stmt::ends_with_block) && self.starts_statement() { continue }
+            if self.current_token.is(&[Token::RightCurly, Token::EOF]) { break }
+
+            let e = match &self.current_token {
+                Token::ID(_) => ParseError::UnexpectedToken(self.current_span.clone(), self.current_token.clone()),
+                t => ParseError::WrongToken(self.current_span.clone(), Token::Semicolon, t.clone()),
+            };
+            self.recover(Box::new(e))?;
+            if self.current_token.is(&[Token::RightCurly, Token::EOF]) { break }
         }
 
-        if let Token::ID(_) = &self.current_token {
-            return Err(ParseError::UnexpectedToken(self.lexer.pos, self.current_token.clone()))
+        return Ok(BlockStmt { statements: res, span: self.span_from(&start) });
+    }
+
+    fn empty(&self) -> Expr {
+        Expr::new(ExprKind::Noop, Span { len: 0, ..self.current_span.clone() })
+    }
+
+    fn starts_expr(&self) -> bool {
+        use Token::*;
+
+        match self.current_token {
+            Number(_) | SizedNumber(..) | Float(_) | Quantity(..) | Str(_) | InterpStr(_) | ID(_) => true,
+            _ => self.current_token.is(&[True, False, LeftParen, If]) || self.current_token.is_geometric_type() ||
+                Self::operator(&self.current_token, true).is_some()
+        }
+    }
+
+    fn starts_statement(&self) -> bool {
+        use Token::*;
+
+        if let Label(_) = self.current_token { return true }
+        self.starts_expr() || self.current_token.is(&[LeftCurly, Let, Ret, Fn, While, For, Loop, Break, Continue])
+    }
+
+    fn statement(&mut self) -> Result<Stmt, Box<ParseError>> {
+        let start = self.current_span.clone();
+        let kind = match &self.current_token {
+            Token::LeftCurly => StmtKind::Block(self.scope_statement()?),
+            Token::Let => self.vardecl_statement()?,
+            Token::Ret => self.return_statement()?,
+            Token::Fn => self.fn_statement()?,
+            //an if starting a statement ends with its block, what follows it is the next statement as in `if c { f() } -1`
+            Token::If => StmtKind::Expr(*self.if_expr()?),
+            Token::Label(_) | Token::While | Token::For | Token::Loop => self.loop_statement()?,
+            Token::Break => { self.eat(Token::Break)?; StmtKind::Break(self.label_ref()?) },
+            Token::Continue => { self.eat(Token::Continue)?; StmtKind::Continue(self.label_ref()?) },
+            _ if self.starts_expr() => self.expr_statement()?,
+            _ => { let e = self.empty(); return Ok(Stmt::new(StmtKind::Expr(e), Span { len: 0, ..start })) }
+        };
+
+        Ok(Stmt::new(kind, self.span_from(&start)))
+    }
+
+    //label_ref: LABEL | empty
+    fn label_ref(&mut self) -> Result<Option<String>, Box<ParseError>> {
+        if let Token::Label(l) = &self.current_token {
+            let l = l.clone();
+            self.eat(Token::Label(l.clone()))?;
+            return Ok(Some(l))
         }
 
-        return Ok(BlockStmt { statements: res });
+        Ok(None)
     }
 
-    fn empty() -> Expr {
-        Expr::Noop
+    //loop_statement: (LABEL COLON)? (WHILE expr | FOR variable IN expr DOTDOT expr | LOOP) scope_statement
+    fn loop_statement(&mut self) -> Result<StmtKind, Box<ParseError>> {
+        use Token::*;
+
+        let label = self.label_ref()?;
+        if label.is_some() { self.eat(Colon)?; }
+
+        match self.current_token {
+            While => {
+                self.eat(While)?;
+                let cond = self.expr()?;
+                let body = self.scope_statement()?;
+                Ok(StmtKind::While(label, *cond, body))
+            }
+            For => {
+                self.eat(For)?;
+                let var = self.variable()?;
+                self.eat(In)?;
+                let from = self.expr()?;
+                self.eat(DotDot)?;
+                let to = self.expr()?;
+                let body = self.scope_statement()?;
+                Ok(StmtKind::For(label, var, from, to, body))
+            }
+            Loop => {
+                self.eat(Loop)?;
+                Ok(StmtKind::Loop(label, self.scope_statement()?))
+            }
+            _ => Err(Box::new(ParseError::UnexpectedToken(self.current_span.clone(), self.current_token.clone())))
+        }
     }
 
-    fn statement(&mut self) -> Result<Stmt, ParseError> {
-        return match &self.current_token {
-            Token::LeftCurly => Ok(Stmt::Block(self.scope_statement()?)),
-            Token::Let => self.vardecl_statement(),
-            Token::ID(_) => self.assignment_statement(),
-            Token::Ret =>  { self.eat(Token::Ret)?; Ok(Stmt::Return(*self.expr()?)) },
-            _ => Ok(Stmt::Expr(Parser::empty()))
+    //return_statement: RETURN expr | RETURN empty
+    fn return_statement(&mut self) -> Result<StmtKind, Box<ParseError>> {
+        self.eat(Token::Ret)?;
+        if self.starts_expr() {
+            return Ok(StmtKind::Return(*self.expr()?))
         }
+
+        Ok(StmtKind::Return(self.empty()))
     }
 
-    fn assignment_statement(&mut self) -> Result<Stmt, ParseError> {
-        let v = self.variable()?;
-        self.eat(Token::Equal)?;
+    //expr_statement: expr | variable ASSIGN expr
+    fn expr_statement(&mut self) -> Result<StmtKind, Box<ParseError>> {
         let expr = self.expr()?;
-        Ok(Stmt::Assign(*v, *expr))
+        if self.current_token != Token::Equal {
+            return Ok(StmtKind::Expr(*expr))
+        }
+
+        if let ExprKind::Var(_) = expr.kind {
+            self.eat(Token::Equal)?;
+            let rhs = self.expr()?;
+            return Ok(StmtKind::Assign(*expr, *rhs))
+        }
+
+        return Err(Box::new(ParseError::UnexpectedToken(self.current_span.clone(), Token::Equal)))
+    }
+
+    //fn_statement: FN NAME LPAREN (param (COMMA param)*)? RPAREN (ARROW type_spec)? scope_statement
+    fn fn_statement(&mut self) -> Result<StmtKind, Box<ParseError>> {
+        use Token::*;
+
+        self.eat(Fn)?;
+        let name = self.variable()?;
+        let ExprKind::Var(name_str) = name.kind else { unreachable!() };
+
+        self.eat(LeftParen)?;
+        let mut params = vec![];
+        while self.current_token != RightParen {
+            if !params.is_empty() { self.eat(Comma)?; }
+            params.push(self.param()?);
+        }
+        self.eat(RightParen)?;
+
+        let ret = if self.current_token == Arrow {
+            self.eat(Arrow)?;
+            self.type_spec()?
+        } else {
+            Type { span: name.span.clone(), ..Type::new(Null, false) }
+        };
+
+        let body = self.scope_statement()?;
+        Ok(StmtKind::Fn(Rc::new(FnDecl { name: name_str, params, ret, body, span: name.span })))
+    }
+
+    //param: NAME type_decl
+    fn param(&mut self) -> Result<Param, Box<ParseError>> {
+        let name = self.variable()?;
+        let t = self.type_decl()?;
+        let ExprKind::Var(name_str) = name.kind else { unreachable!() };
+        Ok(Param { name: name_str, t, span: name.span })
+    }
+
+    //call_args: LPAREN (expr (COMMA expr)*)? RPAREN
+    fn call_args(&mut self) -> Result<Vec<Expr>, Box<ParseError>> {
+        self.eat(Token::LeftParen)?;
+        let mut args = vec![];
+        while self.current_token != Token::RightParen {
+            if !args.is_empty() { self.eat(Token::Comma)?; }
+            args.push(*self.expr()?);
+        }
+        self.eat(Token::RightParen)?;
+        Ok(args)
+    }
+
+    //type_decl: COLON type_spec
+    fn type_decl(&mut self) -> Result<Type, Box<ParseError>> {
+        self.eat(Token::Colon)?;
+        self.type_spec()
     }
 
-    fn type_decl(&mut self) -> Result<Type, ParseError> {
+    //type_spec: MUT? (INT | FLOAT | BOOL | STRING | SIZED_INT | FIXED_TYPE | FIXED LESS NUMBER GREATER | NAME)
+    fn type_spec(&mut self) -> Result<Type, Box<ParseError>> {
         use Token::*;
 
-        self.eat(Colon)?;
         let mut old_token = self.current_token.clone();
         let mut mutable = false;
+        let start = self.current_span.clone();
 
         if let Mutable = old_token {
             self.eat(Mutable)?;
@@ -95,106 +415,404 @@ impl Parser {
             old_token = self.current_token.clone();
         }
 
-        if old_token.is(&[IntType, FloatType]) {
+        if old_token.is(&[IntType, FloatType, BoolType, StrType]) || old_token.is_geometric_type() || matches!(old_token, SizedIntType(_) | FixedType(_) | QuantityType(_)) {
             self.eat(old_token.clone())?;
-            return Ok(Type::new(old_token, mutable));
+            return Ok(Type { t: old_token, mutable, span: self.span_from(&start) });
+        }
+
+        //`fixed<8>` is read as one token, this is the form with spaces as in `fixed < 8 >`
+        if let Fixed = old_token {
+            self.eat(Fixed)?;
+            self.eat(Less)?;
+            let (q, q_span) = (self.current_token.clone(), self.current_span.clone());
+            let Number(q) = q else {
+                return Err(Box::new(ParseError::UnexpectedToken(q_span, q)))
+            };
+            self.eat(Number(q))?;
+            self.eat(Greater)?;
+
+            if !(0..=MAX_FRAC_BITS as i128).contains(&q) { return Err(Box::new(ParseError::InvalidFixedBits(q_span, q))) }
+            return Ok(Type { t: FixedType(q as u8), mutable, span: self.span_from(&start) });
         }
 
         if let ID(id) = old_token {
             self.eat(ID(id.clone()))?;
-            return Ok(Type{ t: Token::ID(id), mutable })
+            return Ok(Type { t: Token::ID(id), mutable, span: self.span_from(&start) })
         }
-        
-        return Err(ParseError::WrongToken(self.lexer.pos, old_token, Token::ID("".to_string())))
+
+        return Err(Box::new(ParseError::WrongToken(self.current_span.clone(), Token::ID("".to_string()), old_token)))
     }
 
-    fn vardecl_statement(&mut self) -> Result<Stmt, ParseError> {
+    fn vardecl_statement(&mut self) -> Result<StmtKind, Box<ParseError>> {
         self.eat(Token::Let)?;
         let left = self.variable()?;
+        let res = self.vardecl_rest(&left);
+        //the checker is told about the name, so that its uses are not reported as undefined too
+        if res.is_err() {
+            if let ExprKind::Var(name) = &left.kind { self.failed_decls.push((name.clone(), left.span.clone())) }
+        }
+        res
+    }
+
+    //vardecl_rest: type_decl (EQUAL expr)?
+    fn vardecl_rest(&mut self, left: &Expr) -> Result<StmtKind, Box<ParseError>> {
         let t = self.type_decl()?;
 
         if let Token::Equal = &self.current_token {
             self.eat(Token::Equal)?;
             let right = self.expr()?;
-            return Ok(Stmt::Let(*left, t, *right))
+            return Ok(StmtKind::Let(left.clone(), t, *right))
         }
 
-        Ok(Stmt::Let(*left, t, Parser::empty()))
+        Ok(StmtKind::Let(left.clone(), t, self.empty()))
     }
 
-    fn variable(&mut self) -> Result<Box<Expr>, ParseError> {
+    fn variable(&mut self) -> Result<Box<Expr>, Box<ParseError>> {
         if let Token::ID(id) = &self.current_token {
             let id = id.clone().to_string();
+            let span = self.current_span.clone();
             self.eat(Token::ID(id.clone()))?;
-            return Ok(Box::new(Expr::Var(id)));
+            return Ok(Box::new(Expr::new(ExprKind::Var(id), span)));
         }
 
-        return Err(ParseError::UnknownToken(self.lexer.pos, 'y'));
+        if self.current_token == Token::EOF { return Err(self.end_of_input()) }
+        return Err(Box::new(ParseError::WrongToken(self.current_span.clone(), Token::ID("".to_string()), self.current_token.clone())));
     }
-    
-    //return INT token value  factor: INTEGER
-    fn factor(&mut self) -> Result<Box<Expr>, ParseError> {
+
+    //primary: literal | if_expr | LPAREN expr RPAREN | geometric_type | variable
+    fn primary(&mut self) -> Result<Box<Expr>, Box<ParseError>> {
         use Token::*;
 
         let old_token = self.current_token.clone();
+        let start = self.current_span.clone();
 
         if let Number(n) = old_token {
             self.eat(Number(n))?;
-            return Ok(Box::new(Expr::NumLit(n)));
+            return Ok(Box::new(Expr::new(ExprKind::NumLit(n), start)));
+        }
+
+        if let SizedNumber(n, kind) = old_token {
+            self.eat(old_token.clone())?;
+            return Ok(Box::new(Expr::new(ExprKind::SizedLit(n, kind), start)));
         }
 
         if let Float(f) = old_token {
             self.eat(Float(f))?;
-            return Ok(Box::new(Expr::FloatLit(f)));
+            return Ok(Box::new(Expr::new(ExprKind::FloatLit(f), start)));
+        }
+
+        if let Quantity(f, ref unit) = old_token {
+            self.eat(old_token.clone())?;
+            return Ok(Box::new(Expr::new(ExprKind::QuantityLit(f, unit.clone()), start)));
+        }
+
+        if let Str(ref s) = old_token {
+            self.eat(old_token.clone())?;
+            return Ok(Box::new(Expr::new(ExprKind::StrLit(s.clone()), start)));
+        }
+
+        if let InterpStr(ref parts) = old_token {
+            self.eat(old_token.clone())?;
+            let mut exprs = vec![];
+            for part in parts {
+                exprs.push(match part {
+                    StrPart::Lit(s) => Expr::new(ExprKind::StrLit(s.clone()), start.clone()),
+                    StrPart::Code(code, span) => Self::interpolation(code, span)?,
+                });
+            }
+            return Ok(Box::new(Expr::new(ExprKind::Interp(exprs), start)));
         }
 
-        if let Minus = old_token {
-            self.eat(Minus)?;
-            return Ok(Box::new(Expr::Prefix(old_token, self.factor()?)))
+        if old_token.is(&[True, False]) {
+            self.eat(old_token.clone())?;
+            return Ok(Box::new(Expr::new(ExprKind::BoolLit(old_token == True), start)));
+        }
+
+        if let If = old_token {
+            return self.if_expr();
         }
 
         if let LeftParen = old_token {
             self.eat(LeftParen)?;
-            let res = self.expr()?;
+            let mut res = self.expr()?;
             self.eat(RightParen)?;
+            res.span = self.span_from(&start);
             return Ok(res);
         }
-        
-        return self.variable()
+
+        //the constructors of vectors, rotations and poses are functions named after their types, as in vec3(1, 2, 3)
+        if old_token.is_geometric_type() {
+            self.eat(old_token.clone())?;
+            if self.current_token != LeftParen {
+                return Err(Box::new(ParseError::WrongToken(self.current_span.clone(), LeftParen, self.current_token.clone())))
+            }
+            let name = Type::new(old_token, false).to_string();
+            return Ok(Box::new(Expr::new(ExprKind::Var(name), start)));
+        }
+
+        if let ID(_) = old_token { return self.variable() }
+        if old_token == EOF { return Err(self.end_of_input()) }
+        Err(Box::new(ParseError::ExpectedExpression(start, old_token)))
     }
 
-    fn term(&mut self) -> Result<Box<Expr>, ParseError> {
-        use Token::*;
+    //parses the expression between the curly braces of an interpolated string
+    fn interpolation(code: &str, span: &Span) -> Result<Expr, Box<ParseError>> {
+        let mut lexer = Lexer::new();
+        lexer.feed_fragment(code.to_string(), span);
+        let mut parser = Parser::new(lexer);
+        parser.read_first_token();
+        if !parser.errors.is_empty() { return Err(Box::new(parser.errors.remove(0))) }
+
+        let expr = match parser.expr() {
+            Err(e) if matches!(*e, ParseError::WaitForInput(..)) => return Err(Box::new(ParseError::UnexpectedToken(parser.current_span, Token::EOF))),
+            res => res?,
+        };
+
+        if parser.current_token != Token::EOF {
+            return Err(Box::new(ParseError::WrongToken(parser.current_span.clone(), Token::RightCurly, parser.current_token.clone())))
+        }
+
+        Ok(*expr)
+    }
 
-        let mut node = self.factor()?;
+    //if_expr: IF expr scope_statement (ELSE (if_expr | scope_statement))?
+    fn if_expr(&mut self) -> Result<Box<Expr>, Box<ParseError>> {
+        let start = self.current_span.clone();
+        self.eat(Token::If)?;
+        let cond = self.expr()?;
+        let then = self.scope_statement()?;
 
-        while self.current_token.is(&[Multiply, Divide]) {
-            let token = self.current_token.clone();
-            self.eat(token.clone())?;
-            node = Box::new(Expr::Infix(node, token, self.factor()?));
+        if self.current_token != Token::Else {
+            return Ok(Box::new(Expr::new(ExprKind::If(cond, then, None), self.span_from(&start))))
         }
 
-        Ok(node)
+        self.eat(Token::Else)?;
+        let otherwise = if self.current_token == Token::If {
+            //`else if` is an else block holding only the nested if
+            let nested = self.if_expr()?;
+            let span = nested.span.clone();
+            BlockStmt { statements: vec![Stmt::new(StmtKind::Expr(*nested), span.clone())], span }
+        } else {
+            self.scope_statement()?
+        };
+
+        Ok(Box::new(Expr::new(ExprKind::If(cond, then, Some(otherwise)), self.span_from(&start))))
     }
 
-    //evaluates the expression
-    fn expr(&mut self) -> Result<Box<Expr>, ParseError> {
-        use Token::*;
-        
-        let mut node = self.term()?;
-        while self.current_token.is(&[Plus, Minus]) {
-            let token = self.current_token.clone();
-            self.eat(token.clone())?;
-            node = Box::new(Expr::Infix(node, token, self.factor()?));
+    //finds the prefix operator, or the infix or postfix one, that the token stands for
+    fn operator(token: &Token, prefix: bool) -> Option<&'static Operator> {
+        OPERATORS.iter().find(|op| op.token == *token && (op.fixity == Fixity::Prefix) == prefix)
+    }
+
+    //expr: expr_bp(0)
+    fn expr(&mut self) -> Result<Box<Expr>, Box<ParseError>> {
+        self.expr_bp(0)
+    }
+
+    //parses an expression whose operators all bind tighter than `min_bp`
+    //expr_bp: (prefix expr_bp | primary) (infix expr_bp | postfix)*
+    fn expr_bp(&mut self, min_bp: u8) -> Result<Box<Expr>, Box<ParseError>> {
+        let start = self.current_span.clone();
+
+        let mut lhs = match Self::operator(&self.current_token, true) {
+            Some(op) => {
+                let token = self.current_token.clone();
+                self.eat(token.clone())?;
+                let rhs = self.expr_bp(op.bp)?;
+                Box::new(Expr::new(ExprKind::Prefix(token, rhs), self.span_from(&start)))
+            }
+            None => self.primary()?,
+        };
+
+        while let Some(op) = Self::operator(&self.current_token, false) {
+            if op.bp <= min_bp { break }
+
+            lhs = match op.fixity {
+                Fixity::Postfix => self.postfix(lhs, &start)?,
+                Fixity::Infix(assoc) => {
+                    let token = self.current_token.clone();
+                    self.eat(token.clone())?;
+                    //a right associative operator lets an operator of the same power take its right hand side
+                    let rhs = match assoc {
+                        Assoc::Left => self.expr_bp(op.bp)?,
+                        Assoc::Right => self.expr_bp(op.bp - 1)?,
+                    };
+                    Box::new(Expr::new(ExprKind::Infix(lhs, token, rhs), self.span_from(&start)))
+                }
+                Fixity::Prefix => unreachable!(),
+            };
+        }
+
+        Ok(lhs)
+    }
+
+    //postfix: call_args | LBRACKET expr RBRACKET | DOT ID | AS type_spec
+    fn postfix(&mut self, lhs: Box<Expr>, start: &Span) -> Result<Box<Expr>, Box<ParseError>> {
+        if self.current_token == Token::As {
+            self.eat(Token::As)?;
+            //the result of a cast is a value, it can't be mutable
+            if self.current_token == Token::Mutable {
+                return Err(Box::new(ParseError::UnexpectedToken(self.current_span.clone(), Token::Mutable)))
+            }
+            let t = self.type_spec()?;
+            return Ok(Box::new(Expr::new(ExprKind::Cast(lhs, t), self.span_from(start))))
+        }
+
+        if self.current_token == Token::Dot {
+            self.eat(Token::Dot)?;
+            let Token::ID(name) = self.current_token.clone() else {
+                return Err(Box::new(ParseError::WrongToken(self.current_span.clone(), Token::ID("".to_string()), self.current_token.clone())))
+            };
+            self.eat(Token::ID(name.clone()))?;
+            return Ok(Box::new(Expr::new(ExprKind::Field(lhs, name), self.span_from(start))))
+        }
+
+        if self.current_token == Token::LeftBracket {
+            self.eat(Token::LeftBracket)?;
+            let index = self.expr()?;
+            self.eat(Token::RightBracket)?;
+            return Ok(Box::new(Expr::new(ExprKind::Index(lhs, index), self.span_from(start))))
+        }
+
+        //only functions declared by name can be called
+        let ExprKind::Var(name) = lhs.kind else {
+            return Err(Box::new(ParseError::UnexpectedToken(self.current_span.clone(), self.current_token.clone())))
+        };
+        let args = self.call_args()?;
+        Ok(Box::new(Expr::new(ExprKind::Call(name, args), self.span_from(start))))
+    }
+
+    //parses everything fed so far, recovering from errors; returns the statements that parsed and every error found
+    pub fn parse(&mut self) -> (BlockStmt, Vec<ParseError>) {
+        self.failed_decls.clear();
+        let mut program = match self.statement_block() {
+            Ok(program) => program,
+            Err(e) => { self.errors.push(*e); BlockStmt::new(self.current_span.clone()) }
+        };
+
+        //a `}` without a matching `{`
+        while self.current_token == Token::RightCurly {
+            self.errors.push(ParseError::UnexpectedToken(self.current_span.clone(), Token::RightCurly));
+            self.bump();
+            match self.statement_block() {
+                Ok(rest) => program.statements.extend(rest.statements),
+                Err(e) => self.errors.push(*e),
+            }
+        }
+
+        (program, std::mem::take(&mut self.errors))
+    }
+
+    //the variables the last parse failed to declare, with the spans of their names
+    pub fn failed_decls(&self) -> &[(String, Span)] {
+        &self.failed_decls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::pretty::describe_stmt;
+
+    //the expression with every operation in parentheses
+    fn show(e: &Expr) -> String {
+        use ExprKind::*;
+
+        let op = |t: &Token| t.to_string().trim_matches('`').to_string();
+        let last = |blk: &BlockStmt| blk.statements.last().map_or("".to_string(), show_stmt);
+        match &e.kind {
+            NumLit(n) => n.to_string(),
+            BoolLit(b) => b.to_string(),
+            Var(name) => name.clone(),
+            Infix(lhs, t, rhs) => format!("({} {} {})", show(lhs), op(t), show(rhs)),
+            Prefix(t, rhs) => format!("({}{})", op(t), show(rhs)),
+            Call(name, args) => format!("{name}({})", args.iter().map(show).collect::<Vec<_>>().join(", ")),
+            Field(value, name) => format!("{}.{name}", show(value)),
+            Index(value, index) => format!("{}[{}]", show(value), show(index)),
+            Cast(value, t) => format!("({} as {t})", show(value)),
+            If(cond, then, None) => format!("(if {} {{ {} }})", show(cond), last(then)),
+            If(cond, then, Some(otherwise)) => format!("(if {} {{ {} }} else {{ {} }})", show(cond), last(then), last(otherwise)),
+            _ => format!("{:?}", e.kind),
         }
+    }
+
+    fn show_stmt(stmt: &Stmt) -> String {
+        match &stmt.kind {
+            StmtKind::Expr(e) => show(e),
+            StmtKind::Let(_, _, e) => format!("{} = {}", describe_stmt(stmt), show(e)),
+            _ => describe_stmt(stmt),
+        }
+    }
+
+    //one string per statement
+    fn parse(code: &str) -> Vec<String> {
+        let mut parser = Parser::new(Lexer::new());
+        parser.feed_next_line(code.to_string(), false);
+        let (tree, errors) = parser.parse();
+        assert!(errors.is_empty(), "{code}: {errors:?}");
+        tree.statements.iter().map(show_stmt).collect()
+    }
+
+    #[test]
+    fn if_statement_is_not_an_operand() {
+        assert_eq!(parse("if true { 1 } else { 2 }\n-1"), ["(if true { 1 } else { 2 })", "(-1)"]);
+        assert_eq!(parse("if c { f() }\n(x)"), ["(if c { f() })", "x"]);
+        assert_eq!(parse("if c { 1 } else if d { 2 }\n-x"), ["(if c { 1 } else { (if d { 2 }) })", "(-x)"]);
+    }
+
+    #[test]
+    fn if_expression_is_an_operand() {
+        assert_eq!(parse("let a: int = if c { 1 } else { 2 } - 1"), ["Let a: int = ((if c { 1 } else { 2 }) - 1)"]);
+        assert_eq!(parse("f(if c { 1 } else { 2 } * 2)"), ["f(((if c { 1 } else { 2 }) * 2))"]);
+    }
+
+    #[test]
+    fn binding_powers_follow_the_operator_table() {
+        assert_eq!(parse("a || b && c == d < e | f ^ g & h << i + j * k ** l"),
+            ["(a || (b && (c == (d < (e | (f ^ (g & (h << (i + (j * (k ** l)))))))))))"]);
+        assert_eq!(parse("a ** b * c + d << e & f ^ g | h < i == j && k || l"),
+            ["(((((((((((a ** b) * c) + d) << e) & f) ^ g) | h) < i) == j) && k) || l)"]);
+    }
+
+    #[test]
+    fn operators_associate_as_in_the_table() {
+        assert_eq!(parse("a - b - c"), ["((a - b) - c)"]);
+        assert_eq!(parse("a / b % c * d"), ["(((a / b) % c) * d)"]);
+        assert_eq!(parse("a == b != c"), ["((a == b) != c)"]);
+        assert_eq!(parse("a ** b ** c"), ["(a ** (b ** c))"]);
+    }
 
-        Ok(node)
+    #[test]
+    fn prefix_and_postfix_operators() {
+        assert_eq!(parse("-a ** 2"), ["(-(a ** 2))"]);
+        assert_eq!(parse("!a == -b * c"), ["((!a) == ((-b) * c))"]);
+        assert_eq!(parse("- -a"), ["(-(-a))"]);
+        assert_eq!(parse("-a as i8 + b"), ["(((-a) as i8) + b)"]);
+        assert_eq!(parse("a * b as float"), ["(a * (b as float))"]);
+        assert_eq!(parse("-f(a)[1].x ** 2"), ["(-(f(a)[1].x ** 2))"]);
     }
 
-    pub fn parse(&mut self) -> Result<BlockStmt, ParseError> {
-        let program = self.statement_block()?;
-        if self.current_token != Token::EOF { return Err(ParseError::WrongToken(self.lexer.pos, Token::EOF, self.current_token.clone())) }
+    //the error of code that ends too early, with where it ends and the delimiter left open
+    fn end_of_input(code: &str) -> (Span, Option<Spanned<Token>>) {
+        let mut parser = Parser::new(Lexer::new());
+        parser.feed_next_line(code.to_string(), false);
+        match parser.parse().1.as_slice() {
+            [ParseError::WaitForInput(end, open)] => (end.clone(), open.clone()),
+            errors => panic!("{code}: {errors:?}"),
+        }
+    }
+
+    #[test]
+    fn end_of_input_points_at_the_unclosed_delimiter() {
+        let (end, open) = end_of_input("fn f() {\n  g(1,");
+        assert_eq!((end.line, end.col), (2, 7));
+        let open = open.unwrap();
+        assert_eq!((open.node, open.span.line, open.span.col), (Token::LeftParen, 2, 4));
+
+        let (_, open) = end_of_input("fn f() {\n  g(1)\n");
+        let open = open.unwrap();
+        assert_eq!((open.node, open.span.line, open.span.col), (Token::LeftCurly, 1, 8));
 
-        Ok(program)
+        assert_eq!(end_of_input("let a: int =").1, None);
     }
 }
@@ -0,0 +1,247 @@
+use std::io::{self, BufRead, IsTerminal};
+use std::path::PathBuf;
+
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use unicode_xid::UnicodeXID;
+
+use arm_shell::ast::pretty::pretty_print;
+use arm_shell::diagnostics::{SourceMap, ToDiagnostic};
+use arm_shell::interpreter::Interpreter;
+use arm_shell::lexer::Lexer;
+use arm_shell::object::Object;
+use arm_shell::token::{Token, RESERVED_KEYWORDS};
+
+const PROMPT: &str = ">>> ";
+//shown while the input so far is not a complete statement
+const CONTINUATION_PROMPT: &str = "...> ";
+const HISTORY_FILE: &str = ".arm_shell_history";
+
+//meta-commands, completed along with keywords and names
+const COMMANDS: &[(&str, &str)] = &[
+    (":type", ":type <expr>      infer the type of an expression without running it"),
+    (":ast", ":ast <stmt>       print the syntax tree of the statements"),
+    (":tokens", ":tokens <text>    print the tokens the text is made of"),
+    (":env", ":env              list the defined variables with their types and values"),
+    (":load", ":load <file>      run a file in the current session"),
+    (":reset", ":reset            forget everything defined so far"),
+    (":help", ":help             show this list"),
+];
+
+//completes keywords and the names defined in the interpreter
+struct ReplHelper {
+    names: Vec<String>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].char_indices().rev()
+            .take_while(|(_, c)| c.is_xid_continue() || *c == ':')
+            .last()
+            .map_or(pos, |(i, _)| i);
+        let word = &line[start..pos];
+        if word.is_empty() { return Ok((pos, vec![])) }
+
+        let mut candidates: Vec<String> = RESERVED_KEYWORDS.keys().map(|k| k.to_string())
+            .chain(self.names.iter().cloned())
+            .chain(COMMANDS.iter().map(|(c, _)| c.to_string()))
+            .filter(|c| c.starts_with(word))
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+//reads statements from stdin until it is closed; line editing is only used when stdin is a terminal
+pub fn run(inter: &mut Interpreter) {
+    if !io::stdin().is_terminal() {
+        return run_piped(inter);
+    }
+
+    let mut rl: Editor<ReplHelper, _> = match Editor::new() {
+        Ok(rl) => rl,
+        Err(_) => return run_piped(inter),
+    };
+    rl.set_helper(Some(ReplHelper { names: vec![] }));
+
+    let history = history_path();
+    if let Some(path) = &history {
+        let _ = rl.load_history(path);
+    }
+
+    let mut append = false;
+    loop {
+        if let Some(helper) = rl.helper_mut() {
+            helper.names = inter.names();
+        }
+
+        match rl.readline(if append { CONTINUATION_PROMPT } else { PROMPT }) {
+            Ok(line) => {
+                if !line.trim().is_empty() { let _ = rl.add_history_entry(line.as_str()); }
+                eval_line(inter, line, &mut append);
+            }
+            //Ctrl-C drops the statement being typed
+            Err(ReadlineError::Interrupted) => append = false,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("error: {e}");
+                break;
+            }
+        }
+    }
+
+    if let Some(path) = &history {
+        let _ = rl.save_history(path);
+    }
+}
+
+fn run_piped(inter: &mut Interpreter) {
+    let mut append = false;
+    for line in io::stdin().lock().lines().map_while(Result::ok) {
+        eval_line(inter, line, &mut append);
+    }
+}
+
+//interprets the line, or keeps it and sets `append` if it leaves a statement unfinished
+fn eval_line(inter: &mut Interpreter, line: String, append: &mut bool) {
+    //an empty line gives up on unfinished input and reports why it is incomplete
+    let give_up = line.trim().is_empty();
+    if give_up && !*append { return }
+    if !*append && line.trim_start().starts_with(':') { return command(inter, line.trim()) }
+
+    inter.feed_next_line(line, append);
+    match inter.interpret() {
+        Ok(Object::Null) => (),
+        Ok(res) => println!("{}", res),
+        Err(e) if e.is_incomplete() && !give_up => *append = true,
+        Err(e) => e.emit(inter.sources()),
+    }
+}
+
+//runs a meta-command; they are only recognised at the start of a statement
+fn command(inter: &mut Interpreter, line: &str) {
+    let (cmd, arg) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let arg = arg.trim().to_string();
+
+    match cmd {
+        ":type" => {
+            inter.feed_next_line(arg, &mut false);
+            match inter.type_of() {
+                Ok(Some(t)) => println!("{t}"),
+                Ok(None) => println!("! (always returns early)"),
+                Err(e) => e.emit(inter.sources()),
+            }
+        }
+        ":ast" => {
+            inter.feed_next_line(arg, &mut false);
+            match inter.parse() {
+                Ok(tree) => print!("{}", pretty_print(&tree)),
+                Err(e) => e.emit(inter.sources()),
+            }
+        }
+        ":tokens" => { print_tokens(arg, "<stdin>"); }
+        ":env" => {
+            for (name, obj) in inter.vars() {
+                match obj {
+                    Object::Fn(_) => println!("{obj}"),
+                    Object::Str(ref s) => println!("{name}: {} = {s:?}", obj.type_name()),
+                    _ => println!("{name}: {} = {obj}", obj.type_name()),
+                }
+            }
+        }
+        ":load" => load(inter, &arg),
+        ":reset" => inter.reset(),
+        ":help" => for (_, help) in COMMANDS { println!("{help}") },
+        _ => eprintln!("unknown command `{cmd}`, `:help` lists the commands"),
+    }
+}
+
+//prints the tokens of the text with their positions; returns false if some of the text is not a valid token
+pub fn print_tokens(text: String, file: &str) -> bool {
+    let mut lexer = Lexer::new();
+    lexer.set_file(file);
+    lexer.feed_next_line(text, false);
+    let mut ok = true;
+    loop {
+        match lexer.next_token() {
+            Ok(token) if token.node == Token::EOF => return ok,
+            Ok(token) => {
+                let pos = format!("{}:{}", token.span.line, token.span.col);
+                //interpolated strings are shown as written
+                let text = match token.node {
+                    Token::InterpStr(_) => format!("`{}`", &lexer.source()[token.span.offset..token.span.offset + token.span.byte_len]),
+                    t => t.to_string(),
+                };
+                println!("{pos:<8}{text}");
+            }
+            Err(e) => {
+                let mut sources = SourceMap::new();
+                sources.add(file, lexer.source());
+                e.to_diagnostic().emit(&sources);
+                ok = false;
+            }
+        }
+    }
+}
+
+fn load(inter: &mut Interpreter, path: &str) {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => return eprintln!("cannot read `{path}`: {e}"),
+    };
+
+    inter.set_file(path);
+    inter.feed_next_line(contents, &mut false);
+    match inter.interpret() {
+        Ok(Object::Null) => (),
+        Ok(res) => println!("{}", res),
+        Err(e) => e.emit(inter.sources()),
+    }
+    inter.set_file("<stdin>");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustyline::history::DefaultHistory;
+
+    //where the word before the end of the line starts, and its completions
+    fn complete(line: &str, names: &[&str]) -> (usize, Vec<String>) {
+        let helper = ReplHelper { names: names.iter().map(|n| n.to_string()).collect() };
+        let history = DefaultHistory::new();
+        helper.complete(line, line.len(), &Context::new(&history)).unwrap()
+    }
+
+    #[test]
+    fn completes_keywords_names_and_commands() {
+        assert_eq!(complete("wh", &[]).1, ["while"]);
+        assert_eq!(complete("let x: int = to", &["total", "tool", "count"]), (13, vec!["tool".to_string(), "total".to_string()]));
+        assert_eq!(complete("f", &["f", "f"]).1, ["f", "false", "fixed", "float", "fn", "for"]);
+        assert_eq!(complete(":ty", &[]).1, [":type"]);
+        //the word starts after the last character that can't be in a name
+        assert_eq!(complete("1+ángu", &["ángulo"]), (2, vec!["ángulo".to_string()]));
+        assert_eq!(complete("x + ", &["x"]), (4, vec![]));
+    }
+}
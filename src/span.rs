@@ -0,0 +1,81 @@
+use std::fmt::{ Display, Formatter };
+use std::sync::Arc;
+
+use unicode_width::UnicodeWidthChar;
+
+const BUILTIN_FILE: &str = "<builtin>";
+
+//columns a tab advances by, both in spans and when rendering source lines
+pub const TAB_WIDTH: usize = 4;
+
+//number of terminal columns the character takes up
+pub fn display_width(c: char) -> usize {
+    match c {
+        '\t' => TAB_WIDTH,
+        c => c.width().unwrap_or(0),
+    }
+}
+
+//location of a piece of source text; line and col are 1-based display columns and len is the display width,
+//offset and byte_len locate the same text in bytes
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    pub file: Arc<str>,
+    pub line: usize,
+    pub col: usize,
+    pub len: usize,
+    pub offset: usize,
+    pub byte_len: usize,
+}
+
+impl Span {
+    pub fn new(file: Arc<str>, line: usize, col: usize, len: usize) -> Self {
+        Self { file, line, col, len, offset: 0, byte_len: 0 }
+    }
+
+    //location of the things the host defines rather than the code
+    pub fn builtin() -> Self {
+        Self::new(Arc::from(BUILTIN_FILE), 1, 1, 0)
+    }
+
+    pub fn is_builtin(&self) -> bool {
+        &*self.file == BUILTIN_FILE
+    }
+
+    //span starting at self and ending at the end of `end`; if they are on different lines only the start is kept
+    pub fn to(&self, end: &Span) -> Span {
+        let mut res = self.clone();
+        if end.line == self.line && end.col + end.len > self.col {
+            res.len = end.col + end.len - self.col;
+        }
+        if end.offset + end.byte_len > self.offset {
+            res.byte_len = end.offset + end.byte_len - self.offset;
+        }
+        res
+    }
+}
+
+impl Default for Span {
+    fn default() -> Self {
+        Self::new(Arc::from(""), 1, 1, 0)
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{}:{}:{}", self.file, self.line, self.col)
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Span) -> Self {
+        Self { node, span }
+    }
+}
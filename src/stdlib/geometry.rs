@@ -0,0 +1,66 @@
+use crate::geometry::{Pose, Quat, Vec3};
+use crate::interpreter::Interpreter;
+use crate::object::{NativeFn, Object};
+use crate::token::Token::{self, FloatType, PoseType, QuatType, Vec3Type};
+use crate::units::{BaseUnit, Unit};
+
+use super::math::{finite, float};
+
+//constructors of vectors, rotations and poses, and the operations on them that have no operator
+pub fn load(inter: &mut Interpreter) {
+    inter.register_fn(NativeFn::new("vec3", &[FloatType, FloatType, FloatType], Vec3Type, |args| {
+        Ok(Object::Vec3(Vec3::new(float(&args[0])?, float(&args[1])?, float(&args[2])?)))
+    }));
+
+    //the quaternion w + xi + yj + zk, scaled to length 1
+    inter.register_fn(NativeFn::new("quat", &[FloatType, FloatType, FloatType, FloatType], QuatType, |args| {
+        let q = Quat::new(float(&args[0])?, float(&args[1])?, float(&args[2])?, float(&args[3])?);
+        q.map(Object::Quat).ok_or("the zero quaternion is not a rotation".to_string())
+    }));
+
+    //rotation around an axis by an angle, in radians for plain floats
+    let angle = Token::QuantityType(Unit::base(BaseUnit::Rad));
+    inter.register_fn(NativeFn::new("axis_angle", &[Vec3Type, angle], QuatType, |args| {
+        let q = Quat::from_axis_angle(&vec(&args[0])?, float(&args[1])?);
+        q.map(Object::Quat).ok_or("the zero vector is not an axis".to_string())
+    }).overload(&[Vec3Type, FloatType], QuatType));
+
+    //a pose without an orientation is not rotated
+    inter.register_fn(NativeFn::new("pose", &[Vec3Type, QuatType], PoseType, |args| {
+        let orientation = match args.get(1) {
+            Some(q) => quat(q)?,
+            None => Quat::IDENTITY,
+        };
+        Ok(Object::Pose(Pose::new(vec(&args[0])?, orientation)))
+    }).overload(&[Vec3Type], PoseType));
+
+    inter.register_fn(NativeFn::new("dot", &[Vec3Type, Vec3Type], FloatType, |args| {
+        finite(vec(&args[0])?.dot(&vec(&args[1])?))
+    }));
+    inter.register_fn(NativeFn::new("cross", &[Vec3Type, Vec3Type], Vec3Type, |args| {
+        Ok(Object::Vec3(vec(&args[0])?.cross(&vec(&args[1])?)))
+    }));
+    inter.register_fn(NativeFn::new("norm", &[Vec3Type], FloatType, |args| finite(vec(&args[0])?.norm())));
+    inter.register_fn(NativeFn::new("normalize", &[Vec3Type], Vec3Type, |args| {
+        vec(&args[0])?.normalized().map(Object::Vec3).ok_or("the zero vector has no direction".to_string())
+    }));
+
+    inter.register_fn(NativeFn::new("inverse", &[QuatType], QuatType, |args| match &args[0] {
+        Object::Pose(p) => Ok(Object::Pose(p.inverse())),
+        q => Ok(Object::Quat(quat(q)?.inverse())),
+    }).overload(&[PoseType], PoseType));
+}
+
+fn vec(obj: &Object) -> Result<Vec3, String> {
+    match obj {
+        Object::Vec3(v) => Ok(*v),
+        _ => Err(format!("expected a `vec3`, found `{}`", obj.type_name())),
+    }
+}
+
+fn quat(obj: &Object) -> Result<Quat, String> {
+    match obj {
+        Object::Quat(q) => Ok(*q),
+        _ => Err(format!("expected a `quat`, found `{}`", obj.type_name())),
+    }
+}
@@ -0,0 +1,36 @@
+use crate::interpreter::Interpreter;
+use crate::numeric::{IntKind, SIZED_INTS};
+use crate::object::{NativeFn, Object};
+use crate::token::Token;
+
+//the arithmetic operators fail on overflow; these functions wrap around or saturate instead
+pub fn load(inter: &mut Interpreter) {
+    binary(inter, "wrapping_add", |a, b, kind| kind.wrap(a.wrapping_add(b)));
+    binary(inter, "wrapping_sub", |a, b, kind| kind.wrap(a.wrapping_sub(b)));
+    //wrapping in 128 bits keeps the low bits right
+    binary(inter, "wrapping_mul", |a, b, kind| kind.wrap(a.wrapping_mul(b)));
+
+    binary(inter, "saturating_add", |a, b, kind| kind.saturate(a + b));
+    binary(inter, "saturating_sub", |a, b, kind| kind.saturate(a - b));
+    binary(inter, "saturating_mul", |a, b, kind| match a.checked_mul(b) {
+        Some(v) => kind.saturate(v),
+        None if (a < 0) != (b < 0) => kind.min(),
+        None => kind.max(),
+    });
+}
+
+//function of two integers of the same type, with one overload for each integer type
+fn binary(inter: &mut Interpreter, name: &str, f: fn(i128, i128, IntKind) -> i128) {
+    let mut native = NativeFn::new(name, &[Token::IntType, Token::IntType], Token::IntType, move |args| {
+        match (args[0].as_integer(), args[1].as_integer()) {
+            (Some((a, kind)), Some((b, _))) => Ok(Object::integer(f(a, b, kind), kind)),
+            _ => Err(format!("expected two integers, found `{}` and `{}`", args[0].type_name(), args[1].type_name())),
+        }
+    });
+
+    for (_, kind) in SIZED_INTS {
+        let t = Token::SizedIntType(*kind);
+        native = native.overload(&[t.clone(), t.clone()], t);
+    }
+    inter.register_fn(native);
+}
@@ -0,0 +1,171 @@
+use std::cmp::Ordering;
+
+use crate::interpreter::Interpreter;
+use crate::numeric::checked_pow;
+use crate::object::{NativeFn, Object};
+use crate::token::Token::{self, FloatType, IntType};
+use crate::units::{BaseUnit, Unit};
+
+pub fn load(inter: &mut Interpreter) {
+    inter.register_const("PI", Object::Float(std::f64::consts::PI));
+    inter.register_const("E", Object::Float(std::f64::consts::E));
+
+    trig(inter, "sin", f64::sin);
+    trig(inter, "cos", f64::cos);
+    trig(inter, "tan", f64::tan);
+    unary(inter, "deg_to_rad", f64::to_radians);
+    unary(inter, "rad_to_deg", f64::to_degrees);
+
+    inter.register_fn(NativeFn::new("sqrt", &[FloatType], FloatType, |args| {
+        let x = float(&args[0])?;
+        if x < 0.0 { return Err(format!("cannot take the square root of the negative number {x}")) }
+        finite(x.sqrt())
+    }).overload(&[IntType], FloatType));
+
+    inter.register_fn(NativeFn::new("atan2", &[FloatType, FloatType], FloatType, |args| {
+        finite(float(&args[0])?.atan2(float(&args[1])?))
+    }).overload(&[IntType, IntType], FloatType));
+
+    inter.register_fn(NativeFn::new("pow", &[IntType, IntType], IntType, |args| match (&args[0], &args[1]) {
+        (Object::Int(base), Object::Int(exp)) => {
+            if *exp < 0 { return Err(format!("cannot raise an int to the negative power {exp}")) }
+            match checked_pow(*base as i128, *exp as i128).and_then(|i| i64::try_from(i).ok()) {
+                Some(i) => Ok(Object::Int(i)),
+                None => Err("integer overflow".to_string()),
+            }
+        }
+        (base, exp) => finite(float(base)?.powf(float(exp)?)),
+    }).overload(&[FloatType, FloatType], FloatType));
+
+    inter.register_fn(NativeFn::new("abs", &[IntType], IntType, |args| match args[0] {
+        Object::Int(i) => i.checked_abs().map(Object::Int).ok_or("integer overflow".to_string()),
+        ref x => Ok(Object::Float(float(x)?.abs())),
+    }).overload(&[FloatType], FloatType));
+
+    //min and max take two or more numbers of the same type
+    inter.register_fn(NativeFn::new("min", &[IntType, IntType], IntType, |args| extreme(args, Ordering::Less))
+        .variadic(IntType)
+        .overload(&[FloatType, FloatType], FloatType)
+        .variadic(FloatType));
+    inter.register_fn(NativeFn::new("max", &[IntType, IntType], IntType, |args| extreme(args, Ordering::Greater))
+        .variadic(IntType)
+        .overload(&[FloatType, FloatType], FloatType)
+        .variadic(FloatType));
+
+    inter.register_fn(NativeFn::new("clamp", &[IntType, IntType, IntType], IntType, |args| {
+        let (x, lo, hi) = (&args[0], &args[1], &args[2]);
+        if compare(lo, hi)? == Ordering::Greater {
+            return Err(format!("the lower bound {lo} is greater than the upper bound {hi}"))
+        }
+        if compare(x, lo)? == Ordering::Less { return Ok(lo.clone()) }
+        if compare(x, hi)? == Ordering::Greater { return Ok(hi.clone()) }
+        Ok(x.clone())
+    }).overload(&[FloatType, FloatType, FloatType], FloatType));
+
+    rounding(inter, "floor", f64::floor);
+    rounding(inter, "ceil", f64::ceil);
+    rounding(inter, "round", f64::round);
+}
+
+//the value of a numeric argument; ints are converted to float and floats with a unit are taken in that unit
+pub(crate) fn float(obj: &Object) -> Result<f64, String> {
+    match obj {
+        Object::Int(i) => Ok(*i as f64),
+        Object::Float(f) | Object::Quantity(f, _) => Ok(*f),
+        _ => Err(format!("expected a number, found `{}`", obj.type_name())),
+    }
+}
+
+pub(crate) fn finite(f: f64) -> Result<Object, String> {
+    if !f.is_finite() { return Err(format!("the result {f} is not a finite number")) }
+    Ok(Object::Float(f))
+}
+
+//function of one number returning a float
+fn unary(inter: &mut Interpreter, name: &str, f: fn(f64) -> f64) {
+    inter.register_fn(NativeFn::new(name, &[FloatType], FloatType, move |args| finite(f(float(&args[0])?)))
+        .overload(&[IntType], FloatType));
+}
+
+//trigonometric function of plain numbers in radians, or of angles in any unit, which the checker converts to radians
+fn trig(inter: &mut Interpreter, name: &str, f: fn(f64) -> f64) {
+    let angle = Token::QuantityType(Unit::base(BaseUnit::Rad));
+    inter.register_fn(NativeFn::new(name, &[FloatType], FloatType, move |args| finite(f(float(&args[0])?)))
+        .overload(&[IntType], FloatType)
+        .overload(&[angle], FloatType));
+}
+
+//rounds floats to a whole float; ints are already whole and are returned as they are
+fn rounding(inter: &mut Interpreter, name: &str, f: fn(f64) -> f64) {
+    inter.register_fn(NativeFn::new(name, &[FloatType], FloatType, move |args| match args[0] {
+        Object::Int(i) => Ok(Object::Int(i)),
+        ref x => finite(f(float(x)?)),
+    }).overload(&[IntType], IntType));
+}
+
+//ints are compared exactly, mixed with floats they are converted first
+fn compare(a: &Object, b: &Object) -> Result<Ordering, String> {
+    if let (Object::Int(a), Object::Int(b)) = (a, b) { return Ok(a.cmp(b)) }
+    Ok(float(a)?.partial_cmp(&float(b)?).unwrap_or(Ordering::Equal))
+}
+
+//the first argument that compares as `ord` to all the others: the smallest one for Less, the largest one for Greater
+fn extreme(args: &[Object], ord: Ordering) -> Result<Object, String> {
+    let mut res = &args[0];
+    for arg in &args[1..] {
+        if compare(arg, res)? == ord { res = arg }
+    }
+    Ok(res.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{Interpreter, InterpreterError};
+    use crate::lexer::Lexer;
+    use crate::object::Object;
+    use crate::parser::Parser;
+
+    fn run(code: &str) -> Result<Object, InterpreterError> {
+        let mut inter = Interpreter::new(Parser::new(Lexer::new()));
+        inter.feed_next_line(code.to_string(), &mut false);
+        inter.interpret()
+    }
+
+    fn holds(code: &str) {
+        match run(code) {
+            Ok(Object::Bool(true)) => (),
+            res => panic!("{code}: {res:?}"),
+        }
+    }
+
+    #[test]
+    fn functions_and_constants() {
+        holds("abs(sin(PI)) < 1e-12 && cos(0.0) == 1.0 && abs(tan(PI / 4.0) - 1.0) < 1e-12");
+        holds("abs(sin(90deg) - 1.0) < 1e-12 && sin(0) == 0.0");
+        holds("atan2(1.0, 1.0) == PI / 4.0 && atan2(0, -1) == PI");
+        holds("sqrt(16) == 4.0 && sqrt(2.25) == 1.5");
+        holds("pow(2, 10) == 1024 && pow(2.0, 0.5) == sqrt(2.0) && pow(-1, 9223372036854775807) == -1");
+        holds("abs(-3) == 3 && abs(-2.5) == 2.5");
+        holds("min(3, 1, 2) == 1 && max(3, 1, 2) == 3 && min(1.5, -0.5) == -0.5 && max(1.5, 2.5, 0.5) == 2.5");
+        holds("clamp(5, 0, 3) == 3 && clamp(-1, 0, 3) == 0 && clamp(0.5, 0.0, 1.0) == 0.5");
+        holds("floor(1.5) == 1.0 && ceil(1.5) == 2.0 && round(2.5) == 3.0 && round(-2.5) == -3.0 && floor(7) == 7");
+        holds("deg_to_rad(180.0) == PI && rad_to_deg(PI) == 180.0 && E > 2.718 && E < 2.719");
+    }
+
+    #[test]
+    fn operators() {
+        holds("7 % 3 == 1 && -7 % 3 == -1 && 7.5 % 2.0 == 1.5");
+        holds("2 ** 10 == 1024 && 2.0 ** -1.0 == 0.5 && -2 ** 2 == -4 && 2 ** 3 ** 2 == 512");
+    }
+
+    #[test]
+    fn invalid_arguments_are_runtime_errors() {
+        for code in ["sqrt(-1.0)", "pow(2, -1)", "pow(2, 64)", "clamp(1, 3, 0)", "abs(-9223372036854775807 - 1)", "1 % 0"] {
+            assert!(run(code).is_err(), "{code}");
+        }
+        //the types are checked before running
+        for code in ["sqrt(true)", "min(1)", "min(1, true)", "clamp(1, 2)", "sin(1mm)"] {
+            assert!(matches!(run(code), Err(InterpreterError::CheckFailed(_))), "{code}");
+        }
+    }
+}
@@ -0,0 +1,13 @@
+//functions and constants every interpreter starts with
+
+use crate::interpreter::Interpreter;
+
+pub mod math;
+pub mod integer;
+pub mod geometry;
+
+pub fn load(inter: &mut Interpreter) {
+    math::load(inter);
+    integer::load(inter);
+    geometry::load(inter);
+}
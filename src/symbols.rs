@@ -1,6 +1,12 @@
 use std::collections::HashMap;
-use crate::ast::ast::{BlockStmt, Expr, Stmt, Type};
+use std::rc::Rc;
+use std::fmt::{ Display, Formatter };
+use crate::ast::ast::{BlockStmt, Expr, ExprKind, FnDecl, Stmt, StmtKind, Type};
 use crate::token::Token;
+use crate::span::Span;
+use crate::diagnostics::{Diagnostic, ToDiagnostic};
+use crate::numeric::{fixed_from_f64, fixed_from_int, IntKind, SIZED_INTS};
+use crate::units::Unit;
 
 macro_rules! map(
     { $($key:expr => $value:expr),+ } => {
@@ -16,139 +22,1096 @@ macro_rules! map(
 
 #[derive(Debug)]
 pub enum SymbolError {
-    SomeError
+    UndefinedVariable { name: String, span: Span },
+    UnknownType { name: String, span: Span },
+    TypeMismatch { expected: Type, found: Type, span: Span },
+    InvalidOperands { op: Token, lhs: Type, rhs: Type, span: Span },
+    InvalidOperand { op: Token, rhs: Type, span: Span },
+    //a field that values of the type don't have, or any field of a value that has none
+    UnknownField { t: Type, field: String, span: Span },
+    //floats whose units measure different things, such as a length added to an angle
+    IncompatibleUnits { op: Token, lhs: Type, rhs: Type, span: Span },
+    //indexing a value that is not a string
+    NotIndexable { found: Type, span: Span },
+    AssignToImmutable { name: String, span: Span, decl: Span },
+    Redefinition { name: String, span: Span, previous: Span },
+    //break or continue used outside of a loop
+    OutsideLoop { keyword: Token, span: Span },
+    UndefinedLabel { label: String, span: Span },
+    UndefinedFunction { name: String, span: Span },
+    //`variadic` functions take at least `expected` arguments
+    ArityMismatch { name: String, expected: usize, variadic: bool, found: usize, span: Span },
+    //none of the signatures of an overloaded function accepts the argument types
+    NoMatchingOverload { name: String, found: Vec<Type>, candidates: Vec<FnSignature>, span: Span },
+    //functions can only be declared in the global scope
+    NestedFunction { name: String, span: Span },
+    InvalidCast { from: Type, to: Type, span: Span },
+    //a number literal used as a value of a type that can't hold it
+    LiteralOutOfRange { value: String, t: Type, span: Span },
+}
+
+impl SymbolError {
+    pub fn span(&self) -> &Span {
+        match self {
+            Self::UndefinedVariable { span, .. } => span,
+            Self::UnknownType { span, .. } => span,
+            Self::TypeMismatch { span, .. } => span,
+            Self::InvalidOperands { span, .. } => span,
+            Self::InvalidOperand { span, .. } => span,
+            Self::IncompatibleUnits { span, .. } => span,
+            Self::UnknownField { span, .. } => span,
+            Self::NotIndexable { span, .. } => span,
+            Self::AssignToImmutable { span, .. } => span,
+            Self::Redefinition { span, .. } => span,
+            Self::OutsideLoop { span, .. } => span,
+            Self::UndefinedLabel { span, .. } => span,
+            Self::UndefinedFunction { span, .. } => span,
+            Self::ArityMismatch { span, .. } => span,
+            Self::NoMatchingOverload { span, .. } => span,
+            Self::NestedFunction { span, .. } => span,
+            Self::InvalidCast { span, .. } => span,
+            Self::LiteralOutOfRange { span, .. } => span,
+        }
+    }
+}
+
+impl ToDiagnostic for SymbolError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        let diag = match self {
+            Self::UndefinedVariable { name, .. } =>
+                Diagnostic::error(format!("Cannot find variable `{name}` in this scope"))
+                    .with_label("not found in this scope"),
+            Self::UnknownType { name, .. } =>
+                Diagnostic::error(format!("Unknown type `{name}`"))
+                    .with_label("not a type"),
+            Self::TypeMismatch { expected, found, .. } => {
+                let diag = Diagnostic::error("Mismatched types")
+                    .with_label(format!("expected `{expected}`, found `{found}`"));
+                let diag = match units_note(expected, found) {
+                    Some(note) => diag.with_note(note),
+                    None => diag,
+                };
+                match cast_help(expected, found) {
+                    Some(help) => diag.with_help(help),
+                    None => diag,
+                }
+            }
+            Self::InvalidOperands { op, lhs, rhs, .. } => {
+                let note = match lhs.t.is_geometric_type() || rhs.t.is_geometric_type() {
+                    true => "vectors are added to vectors and scaled by floats, rotations and poses are multiplied by each other and by vectors",
+                    false => operand_note(op),
+                };
+                let diag = Diagnostic::error(format!("Cannot apply {op} to `{lhs}` and `{rhs}`"))
+                    .with_label("invalid operand types")
+                    .with_note(note);
+                //bitwise operators need integers, so only the other operand is worth converting to
+                let int_only = op.is(&[Token::BitAnd, Token::BitOr, Token::BitXor, Token::ShiftLeft, Token::ShiftRight]);
+                match cast_help(lhs, rhs) {
+                    Some(_) if int_only && lhs.t.is_integer_type() => diag.with_help(format!("convert the right operand with `as {lhs}`")),
+                    Some(_) if int_only && rhs.t.is_integer_type() => diag.with_help(format!("convert the left operand with `as {rhs}`")),
+                    Some(_) => diag.with_help(format!("convert one of the operands with `as {lhs}` or `as {rhs}`")),
+                    None => diag,
+                }
+            }
+            Self::UnknownField { t, field, .. } => {
+                let names: Vec<&str> = fields(&t.t).into_iter().map(|(name, _)| name).collect();
+                let note = match names.is_empty() {
+                    true => "only `vec3`, `quat` and `pose` values have fields".to_string(),
+                    false => format!("`{t}` has the fields {}", names.join(", ")),
+                };
+                Diagnostic::error(format!("No field `{field}` on type `{t}`"))
+                    .with_label("unknown field")
+                    .with_note(note)
+            }
+            Self::IncompatibleUnits { op, lhs, rhs, .. } => {
+                let diag = Diagnostic::error(format!("Cannot apply {op} to `{lhs}` and `{rhs}`"))
+                    .with_label("incompatible units")
+                    .with_note(units_note(lhs, rhs).unwrap_or_default());
+                match unit_help(lhs, rhs) {
+                    Some(help) => diag.with_help(help),
+                    None => diag,
+                }
+            }
+            Self::InvalidOperand { op, rhs, .. } =>
+                Diagnostic::error(format!("Cannot apply unary {op} to `{rhs}`"))
+                    .with_label("invalid operand type"),
+            Self::NotIndexable { found, .. } =>
+                Diagnostic::error(format!("Cannot index into a value of type `{found}`"))
+                    .with_label("cannot be indexed")
+                    .with_note("only `string` values can be indexed"),
+            Self::AssignToImmutable { name, decl, .. } =>
+                Diagnostic::error(format!("Cannot assign twice to immutable variable `{name}`"))
+                    .with_label("cannot assign twice to immutable variable")
+                    .with_note(format!("`{name}` is declared at {decl}"))
+                    .with_help(format!("make the variable mutable: `let {name}: mut ...`")),
+            Self::Redefinition { name, previous, .. } => {
+                let note = match previous.is_builtin() {
+                    true => format!("`{name}` is predefined"),
+                    false => format!("previous definition at {previous}"),
+                };
+                Diagnostic::error(format!("`{name}` is already defined in this scope"))
+                    .with_label("redefined here")
+                    .with_note(note)
+            }
+            Self::OutsideLoop { keyword, .. } =>
+                Diagnostic::error(format!("{keyword} outside of a loop"))
+                    .with_label("cannot be used outside of a loop"),
+            Self::UndefinedLabel { label, .. } =>
+                Diagnostic::error(format!("Use of undeclared label `'{label}`"))
+                    .with_label("no enclosing loop has this label"),
+            Self::UndefinedFunction { name, .. } =>
+                Diagnostic::error(format!("Cannot find function `{name}` in this scope"))
+                    .with_label("not found in this scope"),
+            Self::ArityMismatch { name, expected, variadic, found, .. } => {
+                let expected = if *variadic { format!("at least {expected}") } else { expected.to_string() };
+                Diagnostic::error(format!("Function `{name}` takes {expected} argument(s) but {found} were supplied"))
+                    .with_label(format!("expected {expected} argument(s)"))
+            }
+            Self::NoMatchingOverload { name, found, candidates, .. } => {
+                let found: Vec<String> = found.iter().map(Type::to_string).collect();
+                let candidates: Vec<String> = candidates.iter().map(|c| format!("`{name}{c}`")).collect();
+                Diagnostic::error(format!("No version of `{name}` takes ({})", found.join(", ")))
+                    .with_label("no matching signature")
+                    .with_note(format!("candidates are {}", candidates.join(", ")))
+            }
+            Self::NestedFunction { name, .. } =>
+                Diagnostic::error(format!("Function `{name}` is not declared in the global scope"))
+                    .with_label("declared inside a block")
+                    .with_help("move the declaration to the top level of the script"),
+            Self::InvalidCast { from, to, .. } =>
+                Diagnostic::error(format!("Cannot cast `{from}` as `{to}`"))
+                    .with_label("invalid cast")
+                    .with_note(units_note(from, to).unwrap_or("only numbers can be cast, to other numeric types".to_string())),
+            Self::LiteralOutOfRange { value, t, .. } =>
+                Diagnostic::error(format!("Literal `{value}` out of range for `{t}`"))
+                    .with_label(format!("does not fit in `{t}`")),
+        };
+
+        diag.with_span(self.span())
+    }
+}
+
+//explains which operand types an infix operator accepts
+fn operand_note(op: &Token) -> &'static str {
+    use Token::*;
+
+    match op {
+        And | Or => "both operands must be `bool`",
+        EqualEqual | NotEqual => "both operands must have the same type",
+        Plus | Less | LessEqual | Greater | GreaterEqual => "both operands must be numbers or strings and have the same type",
+        BitAnd | BitOr | BitXor | ShiftLeft | ShiftRight => "both operands must be integers of the same type",
+        Power => "both operands must be integers or floats of the same type",
+        _ => "both operands must be numbers of the same type",
+    }
+}
+
+//the fields of vectors, rotations and poses, with their types; the ones of the position are also fields of a pose
+fn fields(t: &Token) -> Vec<(&'static str, Token)> {
+    use Token::*;
+
+    match t {
+        Vec3Type => vec![("x", FloatType), ("y", FloatType), ("z", FloatType)],
+        QuatType => vec![("w", FloatType), ("x", FloatType), ("y", FloatType), ("z", FloatType)],
+        PoseType => vec![("position", Vec3Type), ("orientation", QuatType), ("x", FloatType), ("y", FloatType), ("z", FloatType)],
+        _ => vec![],
+    }
+}
+
+//explains why two floats can't be converted into each other when their units measure different things
+fn units_note(a: &Type, b: &Type) -> Option<String> {
+    let (ua, ub) = (a.t.unit()?, b.t.unit()?);
+    if ua.same_dimensions(&ub) { return None }
+
+    let measures = |t: &Type, u: &Unit| match u.is_none() {
+        true => format!("`{t}` has no unit"),
+        false => format!("`{t}` measures {}", u.describe()),
+    };
+    Some(format!("{} but {}", measures(a, &ua), measures(b, &ub)))
+}
+
+//suggests giving a unit to the plain float used along with a float with a unit
+fn unit_help(a: &Type, b: &Type) -> Option<String> {
+    let unit = match (a.t.unit()?, b.t.unit()?) {
+        (ua, ub) if ua.is_none() && !ub.is_none() => ub,
+        (ua, ub) if ub.is_none() && !ua.is_none() => ua,
+        _ => return None,
+    };
+    Some(format!("write the number with a unit, as in `1.0{}`, or cast the value with `as float<{unit}>`", unit.suffix()))
+}
+
+//true for two floats with units that measure different things; there is no cast between them
+fn units_clash(a: &Type, b: &Type) -> bool {
+    matches!((&a.t, &b.t), (Token::QuantityType(ua), Token::QuantityType(ub)) if !ua.same_dimensions(ub))
+}
+
+//suggests a cast when a number is used as one of another type
+fn cast_help(expected: &Type, found: &Type) -> Option<String> {
+    if !expected.t.is_numeric_type() || !found.t.is_numeric_type() || expected == found { return None }
+    if units_clash(expected, found) { return None }
+
+    let rounds = expected.t.is_integer_type() && !found.t.is_integer_type();
+    let note = if rounds { ", which rounds toward zero" } else { "" };
+    Some(format!("convert the value with `as {expected}`{note}"))
+}
+
+//value of a number literal, negative ones included
+enum NumLiteral {
+    Int(i128),
+    //literal with a type suffix, which keeps its type
+    Sized(i128, IntKind),
+    Float(f64),
+}
+
+fn literal_value(expr: &Expr) -> Option<NumLiteral> {
+    match &expr.kind {
+        ExprKind::NumLit(n) => Some(NumLiteral::Int(*n)),
+        ExprKind::SizedLit(n, kind) => Some(NumLiteral::Sized(*n, *kind)),
+        ExprKind::FloatLit(f) => Some(NumLiteral::Float(*f)),
+        ExprKind::Prefix(Token::Minus, rhs) => match literal_value(rhs)? {
+            NumLiteral::Int(n) => Some(NumLiteral::Int(-n)),
+            NumLiteral::Sized(n, kind) => Some(NumLiteral::Sized(-n, kind)),
+            NumLiteral::Float(f) => Some(NumLiteral::Float(-f)),
+        },
+        _ => None,
+    }
+}
+
+//true if `expr` is a number literal that can be used as a value of type `t`, if it is in its range
+fn literal_fits(expr: &Expr, t: &Token) -> bool {
+    match literal_value(expr) {
+        Some(NumLiteral::Int(_)) => t.is_numeric_type() && !matches!(t, Token::QuantityType(_)),
+        Some(NumLiteral::Float(_)) => matches!(t, Token::FloatType | Token::FixedType(_)),
+        Some(NumLiteral::Sized(..)) | None => false,
+    }
+}
+
+//the error for an int literal out of the range of its type
+fn literal_out_of_range(n: i128, t: Token, span: &Span) -> Box<SymbolError> {
+    Box::new(SymbolError::LiteralOutOfRange { value: n.to_string(), t: Type::new(t, false), span: span.clone() })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FnSignature {
+    pub params: Vec<Type>,
+    //type of the extra arguments a variadic function takes after `params`
+    pub variadic: Option<Type>,
+    pub ret: Type,
+}
+
+impl FnSignature {
+    //true if the function can be called with arguments of these types
+    pub fn accepts(&self, args: &[Token]) -> bool {
+        let arity_ok = match self.variadic {
+            Some(_) => args.len() >= self.params.len(),
+            None => args.len() == self.params.len(),
+        };
+        let rest = self.variadic.iter().cycle();
+        arity_ok && self.params.iter().chain(rest).zip(args).all(|(p, a)| p.t == *a)
+    }
+}
+
+impl Display for FnSignature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let mut params: Vec<String> = self.params.iter().map(Type::to_string).collect();
+        if let Some(t) = &self.variadic { params.push(format!("{t}...")) }
+        write!(f, "({}) -> {}", params.join(", "), self.ret)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 enum Symbol {
     Type(Type),
-    Var(String, Box<Symbol>),
-    Unknown
+    //name, type and the span of the declaration
+    Var(String, Type, Span),
+    //name, one signature per overload and the span of the declaration
+    Fn(String, Vec<FnSignature>, Span),
+}
+
+impl Symbol {
+    //span of the declaration of a variable or function
+    fn decl_span(&self) -> Option<&Span> {
+        match self {
+            Symbol::Var(_, _, span) | Symbol::Fn(_, _, span) => Some(span),
+            Symbol::Type(_) => None,
+        }
+    }
 }
 
 
-struct SymbolTable {
-    table: HashMap<String, Symbol>
+//one lexical scope; names not found here are looked up in the enclosing scopes
+#[derive(Clone)]
+struct ScopedSymbolTable {
+    table: HashMap<String, Symbol>,
+    parent: Option<Box<ScopedSymbolTable>>,
 }
 
-impl SymbolTable {
+impl ScopedSymbolTable {
 
+    //the outermost scope, holding the builtin types
     pub fn new() -> Self {
-        Self { table: map!(
+        let mut table = map!(
             "int".to_string() => Symbol::Type(Type::new(Token::IntType, false)),
             "float".to_string() => Symbol::Type(Type::new(Token::FloatType, false)),
+            "bool".to_string() => Symbol::Type(Type::new(Token::BoolType, false)),
+            "string".to_string() => Symbol::Type(Type::new(Token::StrType, false)),
             "null".to_string() => Symbol::Type(Type::new(Token::Null, false))
-        ) }
+        );
+        for (name, kind) in SIZED_INTS {
+            table.insert(name.to_string(), Symbol::Type(Type::new(Token::SizedIntType(*kind), false)));
+        }
+
+        Self { table, parent: None }
+    }
+
+    pub fn with_parent(parent: ScopedSymbolTable) -> Self {
+        Self { table: HashMap::new(), parent: Some(Box::new(parent)) }
     }
 
     pub fn define(&mut self, symbol: Symbol) {
-        println!("define: {:?}", symbol);
         match &symbol {
             Symbol::Type(t) => self.table.insert(t.to_string(), symbol),
-            Symbol::Var(name, _) => self.table.insert(name.to_string(), symbol),
-            Symbol::Unknown => panic!()
+            Symbol::Var(name, _, _) | Symbol::Fn(name, _, _) => self.table.insert(name.to_string(), symbol),
         };
     }
 
     pub fn lookup(&self, name: &str) -> Option<&Symbol> {
+        match self.table.get(name) {
+            Some(s) => Some(s),
+            None => self.parent.as_ref()?.lookup(name),
+        }
+    }
+
+    //looks the name up in this scope only, ignoring the enclosing ones
+    pub fn lookup_current(&self, name: &str) -> Option<&Symbol> {
         self.table.get(name)
     }
 }
 
+#[derive(Clone)]
 pub struct SymbolTableBuilder {
-    symtable: SymbolTable
+    symtable: ScopedSymbolTable,
+    //labels of the loops enclosing the statement being checked, innermost last, and whether a break targets them
+    loops: Vec<(Option<String>, bool)>,
+    //return type of the function being checked
+    fn_ret: Option<Type>,
+}
+
+impl Default for SymbolTableBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SymbolTableBuilder {
     pub fn new() -> Self {
-        Self { symtable: SymbolTable::new() }
+        Self { symtable: ScopedSymbolTable::new(), loops: vec![], fn_ret: None }
     }
 
-    fn visit_block(&mut self, blk: &BlockStmt) -> Result<(), SymbolError> {
-        for stmt in &blk.statements { self.visit_stmt(stmt)? }
-        Ok(())
+    fn enter_scope(&mut self) {
+        let parent = std::mem::replace(&mut self.symtable, ScopedSymbolTable::new());
+        self.symtable = ScopedSymbolTable::with_parent(parent);
+    }
+
+    fn leave_scope(&mut self) {
+        if let Some(parent) = self.symtable.parent.take() {
+            self.symtable = *parent;
+        }
+    }
+
+    //returns the type of the value of the block, the one of its last statement, or None if the block always returns early
+    fn visit_block(&mut self, blk: &mut BlockStmt) -> Result<Option<Type>, Box<SymbolError>> {
+        let mut res = Some(Type::new(Token::Null, false));
+        let mut diverges = false;
+
+        for stmt in &mut blk.statements {
+            res = self.visit_stmt(stmt)?;
+            diverges |= res.is_none();
+        }
+
+        if diverges { return Ok(None) }
+        Ok(res)
+    }
+
+    fn visit_scoped_block(&mut self, blk: &mut BlockStmt) -> Result<Option<Type>, Box<SymbolError>> {
+        self.enter_scope();
+        let res = self.visit_block(blk)?;
+        self.leave_scope();
+        Ok(res)
+    }
+
+    //an if without else has no value; with else both branches must have the same type unless one of them returns early
+    fn visit_if(&mut self, cond: &mut Expr, then: &mut BlockStmt, otherwise: &mut Option<BlockStmt>) -> Result<Option<Type>, Box<SymbolError>> {
+        self.expect_type(&Type::new(Token::BoolType, false), cond)?;
+        let then_type = self.visit_scoped_block(then)?;
+
+        let otherwise = match otherwise {
+            Some(blk) => blk,
+            None => return Ok(Some(Type::new(Token::Null, false)))
+        };
+
+        let else_type = self.visit_scoped_block(otherwise)?;
+        match (then_type, else_type) {
+            (Some(t), Some(e)) if t != e => {
+                let span = otherwise.statements.last().map_or(&otherwise.span, |s| &s.span);
+                Err(Box::new(SymbolError::TypeMismatch { expected: t, found: e, span: span.clone() }))
+            }
+            (Some(t), _) => Ok(Some(t)),
+            (None, e) => Ok(e),
+        }
+    }
+
+    fn visit_infix(lhs: &Type, op: &Token, rhs: &Type, span: &Span) -> Result<Type, Box<SymbolError>> {
+        use Token::*;
+
+        //vectors are added and scaled, rotations and poses are composed with `*` and applied to vectors with it
+        let geometric = match (&lhs.t, op, &rhs.t) {
+            (Vec3Type, Plus | Minus, Vec3Type) => Some(Vec3Type),
+            (Vec3Type, Multiply | Divide, FloatType) | (FloatType, Multiply, Vec3Type) => Some(Vec3Type),
+            (QuatType, Multiply, QuatType) => Some(QuatType),
+            (PoseType, Multiply, PoseType) => Some(PoseType),
+            (QuatType | PoseType, Multiply, Vec3Type) => Some(Vec3Type),
+            (t, EqualEqual | NotEqual, _) if t.is_geometric_type() && lhs == rhs => Some(BoolType),
+            _ => None,
+        };
+        if let Some(t) = geometric { return Ok(Type::new(t, false)) }
+
+        if lhs == rhs {
+            let numeric = lhs.t.is_numeric_type();
+            let string = lhs.t == StrType;
+            let res = match op {
+                Plus if string => Some(StrType),
+                Power if matches!(lhs.t, FixedType(_)) => None,
+                Plus | Minus | Multiply | Divide | Modulo | Power if numeric => Some(lhs.t.clone()),
+                BitAnd | BitOr | BitXor | ShiftLeft | ShiftRight if lhs.t.is_integer_type() => Some(lhs.t.clone()),
+                Less | LessEqual | Greater | GreaterEqual if numeric || string => Some(BoolType),
+                EqualEqual | NotEqual if numeric || string || lhs.t == BoolType => Some(BoolType),
+                And | Or if lhs.t == BoolType => Some(BoolType),
+                _ => None
+            };
+
+            if let Some(t) = res { return Ok(Type::new(t, false)) }
+        }
+
+        Err(Box::new(SymbolError::InvalidOperands { op: op.clone(), lhs: lhs.clone(), rhs: rhs.clone(), span: span.clone() }))
     }
 
-    fn visit_infix(lhs: &Symbol, rhs: &Symbol) -> Result<Symbol, SymbolError> {
-        if lhs == rhs { return Ok(lhs.clone()) }
-        Err(SymbolError::SomeError)
+    fn visit_prefix(op: &Token, rhs: &Type, span: &Span) -> Result<Type, Box<SymbolError>> {
+        use Token::*;
+
+        match op {
+            Minus if rhs.t == Vec3Type => Ok(Type::new(Vec3Type, false)),
+            Minus if rhs.t.is_numeric_type() && !matches!(rhs.t, SizedIntType(kind) if !kind.signed()) =>
+                Ok(Type::new(rhs.t.clone(), false)),
+            Not if rhs.t == BoolType => Ok(Type::new(BoolType, false)),
+            BitNot if rhs.t.is_integer_type() => Ok(Type::new(rhs.t.clone(), false)),
+            _ => Err(Box::new(SymbolError::InvalidOperand { op: op.clone(), rhs: rhs.clone(), span: span.clone() }))
+        }
     }
 
-    fn visit_prefix(rhs: &Symbol) -> Result<Symbol, SymbolError> {
-        Ok(rhs.clone())
+    //resolves a type annotation to the type it names, keeping the annotation's mutability
+    fn lookup_type(&self, t: &Type) -> Result<Type, Box<SymbolError>> {
+        //fixed-point types and floats with units are parameterized, and the names of the geometric types are the ones of
+        //their constructors; none of them are in the table
+        if matches!(t.t, Token::FixedType(_) | Token::QuantityType(_)) || t.t.is_geometric_type() { return Ok(t.clone()) }
+
+        match self.symtable.lookup(&t.to_string()) {
+            Some(Symbol::Type(found)) => Ok(Type { mutable: t.mutable, span: t.span.clone(), ..found.clone() }),
+            _ => Err(Box::new(SymbolError::UnknownType { name: t.to_string(), span: t.span.clone() }))
+        }
     }
 
-    fn visit_expr(&mut self, expr: &Expr) -> Result<Symbol, SymbolError> {
-        use Expr::*;
+    //returns the type the expression evaluates to
+    fn visit_expr(&mut self, expr: &mut Expr) -> Result<Type, Box<SymbolError>> {
+        use ExprKind::*;
+
+        //a negated int literal is folded into a negative one, so that its range is checked after the negation
+        if let Prefix(..) = expr.kind {
+            match literal_value(expr) {
+                Some(NumLiteral::Int(n)) => expr.kind = NumLit(n),
+                Some(NumLiteral::Sized(n, kind)) => expr.kind = SizedLit(n, kind),
+                _ => (),
+            }
+        }
 
-        match expr {
-            NumLit(_) => Ok(Symbol::Type(Type::new(Token::IntType, false))),
-            FloatLit(_) => Ok(Symbol::Type(Type::new(Token::FloatType, false))),
-            Noop => Ok(Symbol::Type(Type::new(Token::Null, false))),
+        match &mut expr.kind {
+            //the range of int literals is checked once they have been given the types they are used as
+            NumLit(_) => Ok(Type::new(Token::IntType, false)),
+            FloatLit(_) => Ok(Type::new(Token::FloatType, false)),
+            SizedLit(n, kind) if !kind.contains(*n) => Err(literal_out_of_range(*n, Token::SizedIntType(*kind), &expr.span)),
+            SizedLit(_, kind) => Ok(Type::new(Token::SizedIntType(*kind), false)),
+            FixedLit(_, q) => Ok(Type::new(Token::FixedType(*q), false)),
+            QuantityLit(_, unit) => Ok(Type::new(Token::QuantityType(unit.clone()), false)),
+            BoolLit(_) => Ok(Type::new(Token::BoolType, false)),
+            StrLit(_) => Ok(Type::new(Token::StrType, false)),
+            Interp(parts) => {
+                for part in parts {
+                    let t = self.visit_expr(part)?;
+                    if !t.t.is_numeric_type() && !t.t.is_geometric_type() && !t.t.is(&[Token::BoolType, Token::StrType]) {
+                        return Err(Box::new(SymbolError::TypeMismatch { expected: Type::new(Token::StrType, false), found: t, span: part.span.clone() }))
+                    }
+                }
+                Ok(Type::new(Token::StrType, false))
+            }
+            Noop => Ok(Type::new(Token::Null, false)),
 
-            Var(ref var_id) => {
+            Var(var_id) => {
                 match self.symtable.lookup(var_id) {
-                    None => Err(SymbolError::SomeError),
-                    Some(s) => Ok(s.clone())
+                    Some(Symbol::Var(_, t, _)) => Ok(t.clone()),
+                    _ => Err(Box::new(SymbolError::UndefinedVariable { name: var_id.clone(), span: expr.span.clone() })),
                 }
             }
 
-            Infix(ref lhs, _, ref rhs) => { 
-                let l = self.visit_expr(lhs)?;
-                let r = self.visit_expr(rhs)?;
-                Self::visit_infix(&l, &r)
+            Infix(lhs, op, rhs) => {
+                let mut l = self.visit_expr(lhs)?;
+                let mut r = self.visit_expr(rhs)?;
+                if matches!(l.t, Token::QuantityType(_)) || matches!(r.t, Token::QuantityType(_)) {
+                    return Self::visit_unit_infix(lhs, l, op, rhs, r, &expr.span)
+                }
+
+                //vectors are scaled by floats, which int literals can stand for
+                if l.t.is_geometric_type() || r.t.is_geometric_type() {
+                    let float = Type::new(Token::FloatType, false);
+                    if l.t.is_integer_type() && Self::widen(&float, lhs)? { l = float.clone() }
+                    if r.t.is_integer_type() && Self::widen(&float, rhs)? { r = float }
+                }
+
+                //a literal next to a number of another type takes that type, if the operator works on it
+                if l != r && Self::visit_infix(&r, op, &r, &expr.span).is_ok() && Self::widen(&r, lhs)? {
+                    l = r.clone();
+                } else if l != r && Self::visit_infix(&l, op, &l, &expr.span).is_ok() && Self::widen(&l, rhs)? {
+                    r = l.clone();
+                }
+                Self::visit_infix(&l, op, &r, &expr.span)
             }
-            Prefix(_, ref rhs) => {
+            Prefix(op, rhs) => {
                 let r = self.visit_expr(rhs)?;
-                Self::visit_prefix(&r)
+                Self::visit_prefix(op, &r, &expr.span)
+            }
+            Call(name, args) => self.visit_call(name, args, &expr.span),
+            Field(value, name) => {
+                let t = self.visit_expr(value)?;
+                match fields(&t.t).into_iter().find(|(field, _)| field == name) {
+                    Some((_, field)) => Ok(Type::new(field, false)),
+                    None => Err(Box::new(SymbolError::UnknownField { t, field: name.clone(), span: expr.span.clone() })),
+                }
+            }
+            Index(value, index) => {
+                let t = self.visit_expr(value)?;
+                if t.t != Token::StrType {
+                    return Err(Box::new(SymbolError::NotIndexable { found: t, span: value.span.clone() }))
+                }
+                self.expect_type(&Type::new(Token::IntType, false), index)?;
+                Ok(Type::new(Token::StrType, false))
+            }
+            If(cond, then, otherwise) => {
+                let t = self.visit_if(cond, then, otherwise)?;
+                Ok(t.unwrap_or(Type::new(Token::Null, false)))
+            }
+            Cast(value, t) => {
+                let from = self.visit_expr(value)?;
+                let to = self.lookup_type(t)?;
+                if from != to && (!(from.t.is_numeric_type() && to.t.is_numeric_type()) || units_clash(&from, &to)) {
+                    return Err(Box::new(SymbolError::InvalidCast { from, to, span: expr.span.clone() }))
+                }
+                Ok(Type::new(to.t, false))
             }
         }
     }
 
-    fn visit_stmt(&mut self, statement: &Stmt) -> Result<(), SymbolError> {
-        match statement {
-            Stmt::Expr(ref expr) => { self.visit_expr(expr)?; Ok(()) },
-            Stmt::Block(ref blk) => self.visit_block(blk),
-            Stmt::Let(ref lhs, ref t, ref rhs) => { 
-                if let Expr::Var(name) = lhs {
-                    let var_symbol = match self.symtable.lookup(&t.to_string()) {
-                        None => return Err(SymbolError::SomeError),
-                        Some(s) => s.clone()
-                    };
+    //turns a number literal used where a number of another type is expected into a literal of that type: int literals
+    //can be used as any number and float literals as fixed-point numbers. False if `expr` is not such a literal
+    fn widen(expected: &Type, expr: &mut Expr) -> Result<bool, Box<SymbolError>> {
+        use NumLiteral::*;
+
+        let Some(value) = literal_value(expr) else { return Ok(false) };
+        let text = match value { Int(n) | Sized(n, _) => n.to_string(), Float(f) => f.to_string() };
+        //the range of i64 is the one of the raw value of fixed-point numbers
+        let fits = |v: f64| v >= i64::MIN as f64 && v < i64::MAX as f64;
+        let kind = match (value, &expected.t) {
+            (Int(n), Token::FloatType) => Some(ExprKind::FloatLit(n as f64)),
+            (Int(n), Token::SizedIntType(kind)) => kind.contains(n).then_some(ExprKind::SizedLit(n, *kind)),
+            (Int(n), Token::FixedType(q)) =>
+                fits(n as f64 * (1u64 << q) as f64).then(|| ExprKind::FixedLit(fixed_from_int(n, *q), *q)),
+            (Float(f), Token::FixedType(q)) =>
+                fits(f * (1u64 << q) as f64).then(|| ExprKind::FixedLit(fixed_from_f64(f, *q), *q)),
+            _ => return Ok(false),
+        };
+
+        match kind {
+            Some(kind) => { expr.kind = kind; Ok(true) }
+            None => Err(Box::new(SymbolError::LiteralOutOfRange {
+                value: text, t: expected.clone(), span: expr.span.clone()
+            })),
+        }
+    }
+
+    //arithmetic on floats with units: addition, subtraction, remainder and comparisons need units that measure the
+    //same thing and convert the right operand to the unit of the left one, multiplication and division combine the
+    //units. Int literals are used as plain floats
+    fn visit_unit_infix(lhs: &mut Expr, mut l: Type, op: &Token, rhs: &mut Expr, mut r: Type, span: &Span) -> Result<Type, Box<SymbolError>> {
+        use Token::*;
+
+        let float = Type::new(FloatType, false);
+        if l.t.is_integer_type() && Self::widen(&float, lhs)? { l = float.clone() }
+        if r.t.is_integer_type() && Self::widen(&float, rhs)? { r = float }
 
-                    let expr_symbol = self.visit_expr(rhs)?;
-                    if var_symbol != expr_symbol { return Err(SymbolError::SomeError) }
-                    self.symtable.define(Symbol::Var(name.clone(), Box::new(Symbol::Type(t.clone()))));
+        let (Some(lunit), Some(runit)) = (l.t.unit(), r.t.unit()) else {
+            return Err(Box::new(SymbolError::InvalidOperands { op: op.clone(), lhs: l, rhs: r, span: span.clone() }))
+        };
+
+        let t = match op {
+            Multiply | Divide => {
+                let aligned = lunit.aligned(&runit);
+                if aligned != runit { Self::convert_unit(rhs, aligned.clone()) }
+                Token::float_type(if *op == Multiply { lunit.mul(&aligned) } else { lunit.div(&aligned) })
+            }
+            Plus | Minus | Modulo | Less | LessEqual | Greater | GreaterEqual | EqualEqual | NotEqual => {
+                //zero is the same in every unit, so a plain zero goes with any unit, as in `a > 0.0`
+                let (lunit, runit) = match (Self::unit_zero(lhs, &runit), Self::unit_zero(rhs, &lunit)) {
+                    (true, _) => { l = r.clone(); (runit.clone(), runit) }
+                    (_, true) => { r = l.clone(); (lunit.clone(), lunit) }
+                    _ => (lunit, runit),
+                };
+                if !lunit.same_dimensions(&runit) {
+                    return Err(Box::new(SymbolError::IncompatibleUnits { op: op.clone(), lhs: l, rhs: r, span: span.clone() }))
                 }
+                if lunit != runit { Self::convert_unit(rhs, lunit) }
+                if op.is(&[Plus, Minus, Modulo]) { l.t } else { BoolType }
+            }
+            _ => return Err(Box::new(SymbolError::InvalidOperands { op: op.clone(), lhs: l, rhs: r, span: span.clone() }))
+        };
+
+        Ok(Type::new(t, false))
+    }
+
+    //turns a plain zero literal into a zero with `unit`; returns false, leaving the expression alone, for any other one
+    fn unit_zero(expr: &mut Expr, unit: &Unit) -> bool {
+        if unit.is_none() || !matches!(literal_value(expr), Some(NumLiteral::Float(f)) if f == 0.0) { return false }
+        expr.kind = ExprKind::QuantityLit(0.0, unit.clone());
+        true
+    }
+
+    //makes `expr` evaluate to its value in `unit`, which must measure the same thing as the unit of the value.
+    //Literals are converted in place, other values with a cast
+    fn convert_unit(expr: &mut Expr, unit: Unit) {
+        if let ExprKind::QuantityLit(f, from) = &expr.kind {
+            let factor = from.factor_to(&unit).unwrap_or(1.0);
+            expr.kind = ExprKind::QuantityLit(f * factor, unit);
+            return
+        }
+
+        let span = expr.span.clone();
+        let value = std::mem::replace(expr, Expr::new(ExprKind::Noop, span.clone()));
+        let t = Type::new(Token::QuantityType(unit), false);
+        *expr = Expr::new(ExprKind::Cast(Box::new(value), t), span);
+    }
+
+    //converts a float with a unit used where a float with another unit measuring the same thing is expected, such as
+    //mm where m is expected. False if the types are not such floats
+    fn convert(expected: &Type, found: &Type, expr: &mut Expr) -> bool {
+        match (&expected.t, &found.t) {
+            (Token::QuantityType(to), Token::QuantityType(from)) if from.same_dimensions(to) => {
+                Self::convert_unit(expr, to.clone());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    //checks that the value of `expr` can be stored in a variable of type `expected`
+    fn expect_type(&mut self, expected: &Type, expr: &mut Expr) -> Result<(), Box<SymbolError>> {
+        let found = self.visit_expr(expr)?;
+        if *expected != found && !Self::widen(expected, expr)? && !Self::convert(expected, &found, expr) {
+            return Err(Box::new(SymbolError::TypeMismatch { expected: expected.clone(), found, span: expr.span.clone() }))
+        }
+
+        Ok(())
+    }
+
+    //returns true if a break in the body stops the loop
+    fn visit_loop_body(&mut self, label: &Option<String>, body: &mut BlockStmt) -> Result<bool, Box<SymbolError>> {
+        self.loops.push((label.clone(), false));
+        self.visit_scoped_block(body)?;
+        let (_, broken) = self.loops.pop().unwrap_or_default();
+        Ok(broken)
+    }
 
-                Ok(())
+    //checks that break/continue is inside a loop with the given label; the loop a break stops is marked as broken
+    fn check_loop_target(&mut self, keyword: Token, label: &Option<String>, span: &Span) -> Result<(), Box<SymbolError>> {
+        if self.loops.is_empty() {
+            return Err(Box::new(SymbolError::OutsideLoop { keyword, span: span.clone() }))
+        }
+
+        let target = match label {
+            None => self.loops.len() - 1,
+            Some(l) => match self.loops.iter().rposition(|(own, _)| own == label) {
+                Some(i) => i,
+                None => return Err(Box::new(SymbolError::UndefinedLabel { label: l.clone(), span: span.clone() })),
             },
-            Stmt::Return(ref expr) => { self.visit_expr(expr)?; Ok(()) },
-            Stmt::Assign(ref lhs, ref rhs) => {
-                if let Expr::Var(name) = lhs {
-                    let var_symbol = match self.symtable.lookup(&name) {
-                        None => return Err(SymbolError::SomeError),
-                        Some(s) => match s {
-                                Symbol::Var(_, s) => *s.clone(),
-                                _ => unreachable!()
-                        }
+        };
+
+        if keyword == Token::Break { self.loops[target].1 = true; }
+        Ok(())
+    }
+
+    //fails if `name` is already declared in the current scope
+    fn check_redefinition(&self, name: &str, span: &Span) -> Result<(), Box<SymbolError>> {
+        //shadowing a variable of an enclosing scope is allowed, redefining one in the same scope is not
+        if let Some(previous) = self.symtable.lookup_current(name).and_then(Symbol::decl_span) {
+            return Err(Box::new(SymbolError::Redefinition { name: name.to_string(), span: span.clone(), previous: previous.clone() }))
+        }
+
+        Ok(())
+    }
+
+    fn visit_fn(&mut self, decl: &mut FnDecl) -> Result<(), Box<SymbolError>> {
+        if self.symtable.parent.is_some() {
+            return Err(Box::new(SymbolError::NestedFunction { name: decl.name.clone(), span: decl.span.clone() }))
+        }
+        self.check_redefinition(&decl.name, &decl.span)?;
+
+        let mut params = vec![];
+        for p in &decl.params { params.push(self.lookup_type(&p.t)?); }
+        let ret = match decl.ret.t {
+            Token::Null => decl.ret.clone(),
+            _ => self.lookup_type(&decl.ret)?,
+        };
+
+        //defined before checking the body so that the function can call itself
+        let signature = FnSignature { params: params.clone(), variadic: None, ret: ret.clone() };
+        self.symtable.define(Symbol::Fn(decl.name.clone(), vec![signature], decl.span.clone()));
+
+        self.enter_scope();
+        for (p, t) in decl.params.iter().zip(params) {
+            self.check_redefinition(&p.name, &p.span)?;
+            self.symtable.define(Symbol::Var(p.name.clone(), t, p.span.clone()));
+        }
+
+        let loops = std::mem::take(&mut self.loops);
+        let outer_ret = self.fn_ret.replace(ret.clone());
+        let body = self.visit_scoped_block(&mut decl.body)?;
+        self.fn_ret = outer_ret;
+        self.loops = loops;
+        self.leave_scope();
+
+        //a literal or a float with a unit ending the body is converted to the return type, as if it was returned
+        if let (Some(Stmt { kind: StmtKind::Expr(tail), .. }), Some(found)) = (decl.body.statements.last_mut(), &body) {
+            if *found != ret && (Self::widen(&ret, tail)? || Self::convert(&ret, found, tail)) { return Ok(()) }
+        }
+
+        //without an explicit return the function evaluates to the value of its body
+        match body {
+            Some(found) if ret.t != Token::Null && found != ret => {
+                let span = decl.body.statements.last().map_or(&decl.body.span, |s| &s.span);
+                Err(Box::new(SymbolError::TypeMismatch { expected: ret, found, span: span.clone() }))
+            }
+            _ => Ok(())
+        }
+    }
+
+    fn visit_call(&mut self, name: &str, args: &mut [Expr], span: &Span) -> Result<Type, Box<SymbolError>> {
+        let mut signatures = match self.symtable.lookup(name) {
+            Some(Symbol::Fn(_, signatures, _)) => signatures.clone(),
+            _ => return Err(Box::new(SymbolError::UndefinedFunction { name: name.to_string(), span: span.clone() }))
+        };
+        if signatures.len() > 1 { return self.visit_overloaded_call(name, signatures, args, span) }
+        let signature = signatures.remove(0);
+
+        let arity_ok = match signature.variadic {
+            Some(_) => args.len() >= signature.params.len(),
+            None => args.len() == signature.params.len(),
+        };
+        if !arity_ok {
+            return Err(Box::new(SymbolError::ArityMismatch {
+                name: name.to_string(),
+                expected: signature.params.len(),
+                variadic: signature.variadic.is_some(),
+                found: args.len(),
+                span: span.clone()
+            }))
+        }
+
+        let rest = signature.variadic.iter().cycle();
+        for (t, arg) in signature.params.iter().chain(rest).zip(args) {
+            self.expect_type(t, arg)?;
+        }
+
+        Ok(Type::new(signature.ret.t, false))
+    }
+
+    //picks the first signature that accepts the types of the arguments
+    fn visit_overloaded_call(&mut self, name: &str, candidates: Vec<FnSignature>, args: &mut [Expr], span: &Span) -> Result<Type, Box<SymbolError>> {
+        let mut found = vec![];
+        for arg in args.iter_mut() { found.push(self.visit_expr(arg)?); }
+
+        let tokens: Vec<Token> = found.iter().map(|t| t.t.clone()).collect();
+        if let Some(signature) = candidates.iter().find(|c| c.accepts(&tokens)) {
+            return Ok(Type::new(signature.ret.t.clone(), false))
+        }
+
+        //otherwise the number literals may take the types of the parameters, and floats with units be converted to
+        //the units of the parameters
+        let widened = |c: &FnSignature| -> Vec<Token> {
+            let params = c.params.iter().chain(c.variadic.iter().cycle());
+            params.zip(found.iter().zip(args.iter()))
+                .map(|(p, (t, arg))| {
+                    let converts = matches!((&p.t, &t.t), (Token::QuantityType(to), Token::QuantityType(from)) if from.same_dimensions(to));
+                    if literal_fits(arg, &p.t) || converts { p.t.clone() } else { t.t.clone() }
+                })
+                .collect()
+        };
+        let Some(signature) = candidates.iter().find(|c| c.accepts(&widened(c))) else {
+            return Err(Box::new(SymbolError::NoMatchingOverload { name: name.to_string(), found, candidates, span: span.clone() }))
+        };
+
+        let rest = signature.variadic.iter().cycle();
+        for ((t, arg), found) in signature.params.iter().chain(rest).zip(args).zip(&found) {
+            if t != found && !Self::widen(t, arg)? { Self::convert(t, found, arg); }
+        }
+        Ok(Type::new(signature.ret.t.clone(), false))
+    }
+
+    //returns the type of the value of the statement, or None if it always returns early
+    fn visit_stmt(&mut self, statement: &mut Stmt) -> Result<Option<Type>, Box<SymbolError>> {
+        let null = Some(Type::new(Token::Null, false));
+
+        match &mut statement.kind {
+            StmtKind::Expr(Expr { kind: ExprKind::If(cond, then, otherwise), .. }) => self.visit_if(cond, then, otherwise),
+            StmtKind::Expr(expr) => Ok(Some(self.visit_expr(expr)?)),
+            StmtKind::Block(blk) => self.visit_scoped_block(blk),
+            StmtKind::While(label, cond, body) => {
+                self.expect_type(&Type::new(Token::BoolType, false), cond)?;
+                self.visit_loop_body(label, body)?;
+                Ok(null)
+            }
+            StmtKind::For(label, var, from, to, body) => {
+                let int = Type::new(Token::IntType, false);
+                self.expect_type(&int, from)?;
+                self.expect_type(&int, to)?;
+
+                self.enter_scope();
+                if let ExprKind::Var(name) = &var.kind {
+                    self.symtable.define(Symbol::Var(name.clone(), int, var.span.clone()));
+                }
+                self.visit_loop_body(label, body)?;
+                self.leave_scope();
+                Ok(null)
+            }
+            //a loop no break stops only ends by returning
+            StmtKind::Loop(label, body) => match self.visit_loop_body(label, body)? {
+                true => Ok(null),
+                false => Ok(None),
+            },
+            StmtKind::Break(label) => {
+                self.check_loop_target(Token::Break, label, &statement.span)?;
+                Ok(None)
+            }
+            StmtKind::Continue(label) => {
+                self.check_loop_target(Token::Continue, label, &statement.span)?;
+                Ok(None)
+            }
+            StmtKind::Let(lhs, t, rhs) => {
+                if let ExprKind::Var(name) = &lhs.kind {
+                    self.check_redefinition(name, &lhs.span)?;
+
+                    let var_type = self.lookup_type(t)?;
+                    self.expect_type(&var_type, rhs)?;
+                    self.symtable.define(Symbol::Var(name.clone(), var_type, lhs.span.clone()));
+                }
+
+                Ok(null)
+            },
+            StmtKind::Return(expr) => {
+                match self.fn_ret.clone() {
+                    Some(ret) => self.expect_type(&ret, expr)?,
+                    None => { self.visit_expr(expr)?; }
+                }
+                Ok(None)
+            },
+            StmtKind::Fn(decl) => { self.visit_fn(Rc::make_mut(decl))?; Ok(null) },
+            StmtKind::Assign(lhs, rhs) => {
+                if let ExprKind::Var(name) = &lhs.kind {
+                    let (var_type, decl) = match self.symtable.lookup(name) {
+                        Some(Symbol::Var(_, t, decl)) => (t.clone(), decl.clone()),
+                        _ => return Err(Box::new(SymbolError::UndefinedVariable { name: name.clone(), span: lhs.span.clone() })),
                     };
-                    
-                    if let Symbol::Type(ref t) = var_symbol {
-                        if !t.mutable { return Err(SymbolError::SomeError) }
+
+                    if !var_type.mutable {
+                        return Err(Box::new(SymbolError::AssignToImmutable { name: name.clone(), span: statement.span.clone(), decl }))
                     }
 
-                    let expr_symbol = self.visit_expr(rhs)?;
-                    if var_symbol != expr_symbol { return Err(SymbolError::SomeError) }
+                    self.expect_type(&var_type, rhs)?;
                 }
 
-                Ok(())
+                Ok(null)
             }
         }
     }
 
-    pub fn check(&mut self, ast: &BlockStmt) -> Result<(), SymbolError> {
-        self.visit_block(ast)?;
+    //type of the global variable with the given name
+    pub fn global_type(&self, name: &str) -> Option<Type> {
+        let mut global = &self.symtable;
+        while let Some(ref parent) = global.parent { global = parent; }
+        match global.lookup_current(name) {
+            Some(Symbol::Var(_, t, _)) => Some(t.clone()),
+            _ => None,
+        }
+    }
+
+    //declares an immutable variable in the global scope for a value provided by the host rather than by code
+    pub fn define_global(&mut self, name: &str, t: Type) {
+        let mut global = &mut self.symtable;
+        while let Some(ref mut parent) = global.parent { global = parent; }
+        global.define(Symbol::Var(name.to_string(), t, Span::builtin()));
+    }
+
+    //declares a function implemented by the host in the global scope, with one signature per overload
+    pub fn define_native(&mut self, name: &str, signatures: Vec<FnSignature>) {
+        let mut global = &mut self.symtable;
+        while let Some(ref mut parent) = global.parent { global = parent; }
+        global.define(Symbol::Fn(name.to_string(), signatures, Span::builtin()));
+    }
+
+    //type of the value of the tree, None if it always returns early; nothing it declares is kept
+    pub fn infer(&mut self, ast: &mut BlockStmt) -> Result<Option<Type>, Box<SymbolError>> {
+        let snapshot = self.clone();
+        let res = self.visit_block(ast);
+        *self = snapshot;
+        for stmt in &ast.statements { Self::check_int_literals(stmt)? }
+        res
+    }
+
+    //the int literals still of type int once the others have been converted to the types they are used as must fit in it
+    fn check_int_literals(stmt: &Stmt) -> Result<(), Box<SymbolError>> {
+        let mut res = Ok(());
+        stmt.for_each_expr(&mut |e| match e.kind {
+            ExprKind::NumLit(n) if res.is_ok() && !IntKind::I64.contains(n) => res = Err(literal_out_of_range(n, Token::IntType, &e.span)),
+            _ => (),
+        });
+        res
+    }
+
+    //after an error in a top level statement: goes back to the global scope and declares the variable the statement
+    //would have declared, so that later statements don't report it as undefined
+    fn recover(&mut self, stmt: &Stmt) {
+        while self.symtable.parent.is_some() { self.leave_scope(); }
+        self.loops.clear();
+        self.fn_ret = None;
+
+        if let StmtKind::Let(ref lhs, ref t, _) = stmt.kind {
+            if let (ExprKind::Var(name), Ok(t)) = (&lhs.kind, self.lookup_type(t)) {
+                if self.symtable.lookup_current(name).is_none() {
+                    self.symtable.define(Symbol::Var(name.clone(), t, lhs.span.clone()));
+                }
+            }
+        }
+    }
+
+    //checks the tree, reporting the first error of every top level statement; on error the table is left as it was before the call
+    pub fn check(&mut self, ast: &mut BlockStmt) -> Result<(), Vec<SymbolError>> {
+        let snapshot = self.clone();
+        let mut errors = vec![];
+
+        for stmt in &mut ast.statements {
+            if let Err(e) = self.visit_stmt(stmt).and_then(|_| Self::check_int_literals(stmt)) {
+                errors.push(*e);
+                self.recover(stmt);
+            }
+        }
+
+        if !errors.is_empty() {
+            *self = snapshot;
+            return Err(errors);
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn check(code: &str) -> Result<(), Vec<SymbolError>> {
+        let mut parser = Parser::new(Lexer::new());
+        parser.feed_next_line(code.to_string(), false);
+        let (mut tree, errors) = parser.parse();
+        assert!(errors.is_empty(), "{code}: {errors:?}");
+        SymbolTableBuilder::new().check(&mut tree)
+    }
+
+    //the only error of the code
+    fn error(code: &str) -> SymbolError {
+        match check(code) {
+            Err(mut errors) if errors.len() == 1 => errors.remove(0),
+            res => panic!("{code}: {res:?}"),
+        }
+    }
+
+    #[test]
+    fn semantic_errors() {
+        use SymbolError::*;
+
+        assert!(matches!(error("a + 1"), UndefinedVariable { name, span } if name == "a" && span.col == 1));
+        assert!(matches!(error("let a: number = 1;"), UnknownType { name, .. } if name == "number"));
+        assert!(matches!(error("let a: int = true;"), TypeMismatch { expected, found, .. }
+            if expected.t == Token::IntType && found.t == Token::BoolType));
+        assert!(matches!(error("1 + true"), InvalidOperands { op: Token::Plus, .. }));
+        assert!(matches!(error("!1"), InvalidOperand { op: Token::Not, .. }));
+        assert!(matches!(error("1[0]"), NotIndexable { .. }));
+        assert!(matches!(error("let a: int = 1; a = 2;"), AssignToImmutable { name, decl, .. } if name == "a" && decl.col == 5));
+        assert!(matches!(error("let a: int = 1; let a: int = 2;"), Redefinition { previous, .. } if previous.col == 5));
+        assert!(matches!(error("break"), OutsideLoop { keyword: Token::Break, .. }));
+        assert!(matches!(error("loop { continue 'a }"), UndefinedLabel { label, .. } if label == "a"));
+        assert!(matches!(error("f(1)"), UndefinedFunction { name, .. } if name == "f"));
+        assert!(matches!(error("fn f(a: int) {} f(1, 2)"), ArityMismatch { expected: 1, found: 2, variadic: false, .. }));
+        assert!(matches!(error("{ fn f() {} }"), NestedFunction { name, .. } if name == "f"));
+        assert!(matches!(error("true as int"), InvalidCast { .. }));
+    }
+
+    #[test]
+    fn every_error_is_reported() {
+        let errors = check("let a: int = true;\nb + 1;\nlet c: int = a + 1;\nc = 2;").unwrap_err();
+        let lines: Vec<usize> = errors.iter().map(|e| e.span().line).collect();
+        assert_eq!(lines, [1, 2, 4]);
+    }
+
+    #[test]
+    fn inner_scopes_shadow_and_forget() {
+        assert!(check("let a: int = 1; { let a: bool = true; let b: bool = !a; }").is_ok());
+        assert!(matches!(error("{ let b: int = 1; } b"), SymbolError::UndefinedVariable { .. }));
+        assert!(matches!(error("fn f() -> int { x } let x: int = 1;"), SymbolError::UndefinedVariable { .. }));
+    }
+
+    #[test]
+    fn loop_without_break_diverges() {
+        assert!(check("fn f() -> int { loop { return 3 } }").is_ok());
+        assert!(check("fn f() -> int { loop { loop { break } } }").is_ok());
+        assert!(check("fn f() -> int { 'a: loop { loop { break 'a } } }").is_err());
+        assert!(check("fn f() -> int { loop { if true { break } } }").is_err());
+    }
+
+    #[test]
+    fn literals_are_checked_against_the_type_they_are_used_as() {
+        assert!(check("let a: i8 = -128i8;").is_ok());
+        assert!(check("let a: i8 = 128i8;").is_err());
+        assert!(check("let a: u64 = 18446744073709551615;").is_ok());
+        assert!(check("let a: int = 9223372036854775808;").is_err());
+        assert!(check("let a: int = -9223372036854775808;").is_ok());
+        assert!(check("let a: int = 18446744073709551615 + 1;").is_err());
+    }
+
+    #[test]
+    fn a_plain_zero_goes_with_any_unit() {
+        assert!(check("let a: float<mm> = 1mm; let b: bool = a > 0.0 && 0 <= a && a != -0.0; let c: float<mm> = a + 0;").is_ok());
+
+        let e = error("let a: float<mm> = 1mm; a > 1.0");
+        assert!(matches!(e, SymbolError::IncompatibleUnits { op: Token::Greater, .. }));
+        let help = e.to_diagnostic().help.unwrap();
+        assert_eq!(help, "write the number with a unit, as in `1.0mm`, or cast the value with `as float<mm>`");
+        //nothing helps units that measure different things
+        assert!(error("let a: float<mm> = 1mm; a > 1s").to_diagnostic().help.is_none());
+    }
+}
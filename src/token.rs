@@ -3,6 +3,10 @@ use std::collections::HashMap;
 
 use lazy_static::lazy_static;
 
+use crate::span::Span;
+use crate::numeric::IntKind;
+use crate::units::Unit;
+
 macro_rules! map(
     { $($key:expr => $value:expr),+ } => {
         {
@@ -15,28 +19,89 @@ macro_rules! map(
     }
 );
 
+//piece of an interpolated string literal
+#[derive(Clone, Debug, PartialEq)]
+pub enum StrPart {
+    Lit(String),
+    //source of the expression between the braces and its location
+    Code(String, Span),
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Token {
-    Number(i64),
+    //integer literal without a type suffix, read as i128 so that literals of u64 values too large for `int` are kept
+    Number(i128),
+    //integer literal with a type suffix, such as 255u8
+    SizedNumber(i128, IntKind),
     Float(f64),
+    //float literal with a unit, such as 10mm or 20mm/s
+    Quantity(f64, Unit),
+    Str(String),
+    InterpStr(Vec<StrPart>),
     Equal,
     Plus,
     Minus,
     Multiply,
     Divide,
+    Modulo,
+    Power,
+    BitAnd,
+    BitOr,
+    BitXor,
+    BitNot,
+    ShiftLeft,
+    ShiftRight,
+    EqualEqual,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+    Not,
+    Dot,
+    DotDot,
+    Arrow,
     LeftParen,
     RightParen,
     LeftCurly,
     RightCurly,
+    LeftBracket,
+    RightBracket,
     Semicolon,
     Let,
     ID(String),
+    Label(String),
     Colon,
     Comma,
     IntType,
     FloatType,
+    BoolType,
+    StrType,
+    SizedIntType(IntKind),
+    //fixed-point type with the given number of fractional bits; written fixed<Q>, with the FIXED keyword
+    FixedType(u8),
+    Fixed,
+    //float with a unit, written float<UNIT>
+    QuantityType(Unit),
+    Vec3Type,
+    QuatType,
+    PoseType,
+    True,
+    False,
     Ret,
+    Fn,
+    If,
+    Else,
+    While,
+    For,
+    In,
+    Loop,
+    Break,
+    Continue,
     Mutable,
+    As,
     Null,
     EOF
 }
@@ -47,16 +112,99 @@ lazy_static! {
         "let" => Token::Let,
         "int" => Token::IntType,
         "float" => Token::FloatType,
+        "bool" => Token::BoolType,
+        "string" => Token::StrType,
+        "i8" => Token::SizedIntType(IntKind::I8),
+        "i16" => Token::SizedIntType(IntKind::I16),
+        "i32" => Token::SizedIntType(IntKind::I32),
+        "i64" => Token::IntType,
+        "u8" => Token::SizedIntType(IntKind::U8),
+        "u16" => Token::SizedIntType(IntKind::U16),
+        "u32" => Token::SizedIntType(IntKind::U32),
+        "u64" => Token::SizedIntType(IntKind::U64),
+        "fixed" => Token::Fixed,
+        "vec3" => Token::Vec3Type,
+        "quat" => Token::QuatType,
+        "pose" => Token::PoseType,
+        "true" => Token::True,
+        "false" => Token::False,
         "return" => Token::Ret,
+        "fn" => Token::Fn,
+        "if" => Token::If,
+        "else" => Token::Else,
+        "while" => Token::While,
+        "for" => Token::For,
+        "in" => Token::In,
+        "loop" => Token::Loop,
+        "break" => Token::Break,
+        "continue" => Token::Continue,
         "null" => Token::Null,
-        "mut" => Token::Mutable
+        "mut" => Token::Mutable,
+        "as" => Token::As
     };
 }
 
 
 impl Display for Token {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        write!(f, "{:?}", self)
+        use Token::*;
+
+        match self {
+            Number(n) => write!(f, "`{n}`"),
+            SizedNumber(n, kind) => write!(f, "`{n}{}`", kind.name()),
+            FixedType(q) => write!(f, "`fixed<{q}>`"),
+            Float(fl) => write!(f, "`{fl:?}`"),
+            Quantity(fl, unit) => write!(f, "`{fl}{}`", unit.suffix()),
+            QuantityType(unit) => write!(f, "`float<{unit}>`"),
+            Str(s) => write!(f, "`{s:?}`"),
+            InterpStr(_) => write!(f, "string"),
+            ID(id) if id.is_empty() => write!(f, "identifier"),
+            ID(id) => write!(f, "`{id}`"),
+            Label(l) => write!(f, "`'{l}`"),
+            EOF => write!(f, "end of input"),
+            _ => {
+                let s = match self {
+                    Equal => "=",
+                    Plus => "+",
+                    Minus => "-",
+                    Multiply => "*",
+                    Divide => "/",
+                    Modulo => "%",
+                    Power => "**",
+                    BitAnd => "&",
+                    BitOr => "|",
+                    BitXor => "^",
+                    BitNot => "~",
+                    ShiftLeft => "<<",
+                    ShiftRight => ">>",
+                    EqualEqual => "==",
+                    NotEqual => "!=",
+                    Less => "<",
+                    LessEqual => "<=",
+                    Greater => ">",
+                    GreaterEqual => ">=",
+                    And => "&&",
+                    Or => "||",
+                    Not => "!",
+                    Dot => ".",
+                    DotDot => "..",
+                    Arrow => "->",
+                    LeftParen => "(",
+                    RightParen => ")",
+                    LeftCurly => "{",
+                    RightCurly => "}",
+                    LeftBracket => "[",
+                    RightBracket => "]",
+                    Semicolon => ";",
+                    Colon => ":",
+                    Comma => ",",
+                    //i64 is another name for int
+                    IntType => "int",
+                    _ => RESERVED_KEYWORDS.iter().find(|(_, t)| *t == self).map(|(k, _)| *k).unwrap_or("?"),
+                };
+                write!(f, "`{s}`")
+            }
+        }
     }
 }
 
@@ -69,5 +217,34 @@ impl Token {
 
         return false;
     }
+
+    //true for the types of integers, int and the sized ones
+    pub fn is_integer_type(&self) -> bool {
+        matches!(self, Token::IntType | Token::SizedIntType(_))
+    }
+
+    //true for the types of numbers: integers, float, fixed-point and floats with a unit
+    pub fn is_numeric_type(&self) -> bool {
+        self.is_integer_type() || matches!(self, Token::FloatType | Token::FixedType(_) | Token::QuantityType(_))
+    }
+
+    //true for the types of vectors, rotations and poses
+    pub fn is_geometric_type(&self) -> bool {
+        matches!(self, Token::Vec3Type | Token::QuatType | Token::PoseType)
+    }
+
+    //unit of the values of a float type, the empty one for plain floats; None for other types
+    pub fn unit(&self) -> Option<Unit> {
+        match self {
+            Token::FloatType => Some(Unit::default()),
+            Token::QuantityType(unit) => Some(unit.clone()),
+            _ => None,
+        }
+    }
+
+    //type of floats with the given unit, plain float for the empty one
+    pub fn float_type(unit: Unit) -> Token {
+        if unit.is_none() { Token::FloatType } else { Token::QuantityType(unit) }
+    }
 }
 
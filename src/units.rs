@@ -0,0 +1,282 @@
+//units of measure: the base units scripts can use, and the products and quotients of them that make up the unit of
+//a quantity such as `float<mm/s>`
+use std::fmt::{ Display, Formatter };
+
+//what a unit measures; values can only be converted between units with the same dimensions
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Dimension {
+    Length,
+    Angle,
+    Time,
+}
+
+impl Dimension {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dimension::Length => "length",
+            Dimension::Angle => "angle",
+            Dimension::Time => "time",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BaseUnit {
+    Mm,
+    Cm,
+    M,
+    Deg,
+    Rad,
+    S,
+    Ms,
+}
+
+//the base units, as they are written in the code
+pub const BASE_UNITS: &[(&str, BaseUnit)] = &[
+    ("mm", BaseUnit::Mm),
+    ("cm", BaseUnit::Cm),
+    ("m", BaseUnit::M),
+    ("deg", BaseUnit::Deg),
+    ("rad", BaseUnit::Rad),
+    ("s", BaseUnit::S),
+    ("ms", BaseUnit::Ms),
+];
+
+impl BaseUnit {
+    pub fn from_name(name: &str) -> Option<BaseUnit> {
+        BASE_UNITS.iter().find(|(n, _)| *n == name).map(|(_, u)| *u)
+    }
+
+    pub fn name(&self) -> &'static str {
+        BASE_UNITS.iter().find(|(_, u)| u == self).map(|(n, _)| *n).unwrap_or("?")
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        use BaseUnit::*;
+
+        match self {
+            Mm | Cm | M => Dimension::Length,
+            Deg | Rad => Dimension::Angle,
+            S | Ms => Dimension::Time,
+        }
+    }
+
+    //size of the unit in the reference unit of its dimension: millimetres, radians and seconds
+    fn scale(&self) -> f64 {
+        use BaseUnit::*;
+
+        match self {
+            Mm => 1.0,
+            Cm => 10.0,
+            M => 1000.0,
+            Deg => std::f64::consts::PI / 180.0,
+            Rad => 1.0,
+            S => 1.0,
+            Ms => 0.001,
+        }
+    }
+}
+
+//product of base units raised to nonzero powers, with at most one base unit per dimension, ordered by dimension.
+//The empty unit is the one of plain numbers
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Unit {
+    factors: Vec<(BaseUnit, i8)>,
+}
+
+impl Unit {
+    pub fn base(unit: BaseUnit) -> Unit {
+        Unit { factors: vec![(unit, 1)] }
+    }
+
+    //true for the unit of plain numbers
+    pub fn is_none(&self) -> bool {
+        self.factors.is_empty()
+    }
+
+    fn exponent(&self, dim: Dimension) -> i8 {
+        self.factors.iter().find(|(u, _)| u.dimension() == dim).map_or(0, |(_, e)| *e)
+    }
+
+    //true if both units measure the same thing, so that values can be converted between them
+    pub fn same_dimensions(&self, other: &Unit) -> bool {
+        self.factors.len() == other.factors.len() &&
+            self.factors.iter().all(|(u, e)| other.exponent(u.dimension()) == *e)
+    }
+
+    //size of the unit in the reference units
+    fn scale(&self) -> f64 {
+        self.factors.iter().map(|(u, e)| u.scale().powi(*e as i32)).product()
+    }
+
+    //number a value in this unit is multiplied by to express it in `to`; None if they measure different things
+    pub fn factor_to(&self, to: &Unit) -> Option<f64> {
+        if !self.same_dimensions(to) { return None }
+        Some(self.scale() / to.scale())
+    }
+
+    //`other` with the base units of the dimensions both units have replaced by the ones of self; values are converted
+    //to it before multiplying or dividing them by values in self, so that mm * cm is mm^2 rather than mm*cm
+    pub fn aligned(&self, other: &Unit) -> Unit {
+        let factors = other.factors.iter().map(|(u, e)| {
+            let own = self.factors.iter().find(|(own, _)| own.dimension() == u.dimension());
+            (own.map_or(*u, |(own, _)| *own), *e)
+        });
+        Unit { factors: factors.collect() }
+    }
+
+    //unit of the product of values in the two units; where both have a dimension the base unit of self is kept, so
+    //`other` should be aligned to self first
+    pub fn mul(&self, other: &Unit) -> Unit {
+        let mut factors = self.factors.clone();
+        for (u, e) in &other.factors {
+            match factors.iter_mut().find(|(own, _)| own.dimension() == u.dimension()) {
+                Some((_, own)) => *own = own.saturating_add(*e),
+                None => factors.push((*u, *e)),
+            }
+        }
+        factors.retain(|(_, e)| *e != 0);
+        factors.sort_by_key(|(u, _)| u.dimension());
+        Unit { factors }
+    }
+
+    pub fn div(&self, other: &Unit) -> Unit {
+        let inverse = other.factors.iter().map(|(u, e)| (*u, e.saturating_neg())).collect();
+        self.mul(&Unit { factors: inverse })
+    }
+
+    //what the unit measures, such as length/time
+    pub fn describe(&self) -> String {
+        Self::write_factors(self.factors.iter().map(|(u, e)| (u.dimension().name(), *e)))
+    }
+
+    //the unit as it is written after a number, with no leading 1 for units that only divide: 2/s rather than 21/s
+    pub fn suffix(&self) -> String {
+        let s = self.to_string();
+        match s.strip_prefix('1') {
+            Some(rest) => rest.to_string(),
+            None => s,
+        }
+    }
+
+    //the factors with a positive exponent joined by `*`, followed by the others each after a `/`
+    fn write_factors<'a>(factors: impl Iterator<Item = (&'a str, i8)> + Clone) -> String {
+        let power = |name: &str, e: i8| if e == 1 { name.to_string() } else { format!("{name}^{e}") };
+        let num: Vec<String> = factors.clone().filter(|(_, e)| *e > 0).map(|(n, e)| power(n, e)).collect();
+        let den: Vec<String> = factors.filter(|(_, e)| *e < 0).map(|(n, e)| format!("/{}", power(n, -e))).collect();
+
+        let num = if num.is_empty() { "1".to_string() } else { num.join("*") };
+        format!("{num}{}", den.concat())
+    }
+
+    //reads the unit at the start of `text`, returning it and the number of bytes it takes up, or None if the text
+    //doesn't start with a unit. With `allow_one` the unit can start with `1/`, as in float<1/s>.
+    //unit: (factor | ONE) ((STAR | SLASH) factor)*
+    //factor: NAME (CARET MINUS? DIGIT+)?
+    pub fn parse_prefix(text: &str, allow_one: bool) -> Option<(Unit, usize)> {
+        let (mut unit, mut len) = match text.strip_prefix("1/") {
+            Some(_) if allow_one => (Unit::default(), 1),
+            _ => Self::parse_factor(text)?,
+        };
+
+        loop {
+            let rest = &text[len..];
+            let divide = match rest.chars().next() {
+                Some('*') => false,
+                Some('/') => true,
+                _ => break,
+            };
+            //an operator not followed by a unit is left for the parser, as in 10mm/speed
+            let Some((factor, factor_len)) = Self::parse_factor(&rest[1..]) else { break };
+
+            let clash = factor.factors.iter().any(|(u, _)| unit.factors.iter().any(|(own, _)| own.dimension() == u.dimension() && own != u));
+            if clash { return None }
+
+            unit = if divide { unit.div(&factor) } else { unit.mul(&factor) };
+            len += 1 + factor_len;
+        }
+
+        Some((unit, len))
+    }
+
+    fn parse_factor(text: &str) -> Option<(Unit, usize)> {
+        let name_len = text.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(text.len());
+        let base = BaseUnit::from_name(&text[..name_len])?;
+
+        let rest = &text[name_len..];
+        let Some(exp) = rest.strip_prefix('^') else { return Some((Unit::base(base), name_len)) };
+        let digits = exp.strip_prefix('-').unwrap_or(exp);
+        let digits_len = digits.find(|c: char| !c.is_ascii_digit()).unwrap_or(digits.len());
+        let exp_len = exp.len() - digits.len() + digits_len;
+        //a caret not followed by an exponent is left for the parser
+        match exp[..exp_len].parse::<i8>() {
+            Ok(e) if e != 0 => Some((Unit { factors: vec![(base, e)] }, name_len + 1 + exp_len)),
+            _ => Some((Unit::base(base), name_len)),
+        }
+    }
+}
+
+impl Display for Unit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", Self::write_factors(self.factors.iter().map(|(u, e)| (u.name(), *e))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(text: &str) -> Unit {
+        let (unit, len) = Unit::parse_prefix(text, true).unwrap();
+        assert_eq!(len, text.len(), "{text}");
+        unit
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-12 * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn units_are_read_and_shown() {
+        assert_eq!(unit("mm").to_string(), "mm");
+        assert_eq!(unit("mm/s").to_string(), "mm/s");
+        assert_eq!(unit("s/mm").to_string(), "s/mm");
+        assert_eq!(unit("m*m/s^2").to_string(), "m^2/s^2");
+        assert_eq!(unit("1/s").to_string(), "1/s");
+        assert_eq!(unit("1/s").suffix(), "/s");
+        assert_eq!(unit("deg/ms").describe(), "angle/time");
+    }
+
+    #[test]
+    fn unit_prefix_stops_where_the_unit_does() {
+        assert_eq!(Unit::parse_prefix("mm/speed", false).map(|(u, len)| (u.to_string(), len)), Some(("mm".to_string(), 2)));
+        assert_eq!(Unit::parse_prefix("s^x", false).map(|(_, len)| len), Some(1));
+        assert_eq!(Unit::parse_prefix("1/s", false), None);
+        assert_eq!(Unit::parse_prefix("kg", false), None);
+        //a dimension can only have one base unit
+        assert_eq!(Unit::parse_prefix("mm*cm", false), None);
+    }
+
+    #[test]
+    fn conversion_factors() {
+        assert!(close(unit("cm").factor_to(&unit("mm")).unwrap(), 10.0));
+        assert!(close(unit("mm").factor_to(&unit("m")).unwrap(), 0.001));
+        assert!(close(unit("deg").factor_to(&unit("rad")).unwrap(), std::f64::consts::PI / 180.0));
+        assert!(close(unit("m/s").factor_to(&unit("mm/ms")).unwrap(), 1.0));
+        assert!(close(unit("1/s").factor_to(&unit("1/ms")).unwrap(), 0.001));
+        assert!(close(unit("m^2").factor_to(&unit("cm^2")).unwrap(), 10000.0));
+        assert_eq!(unit("mm").factor_to(&unit("s")), None);
+        assert_eq!(unit("mm").factor_to(&unit("mm^2")), None);
+        assert_eq!(unit("mm/s").factor_to(&unit("mm")), None);
+    }
+
+    #[test]
+    fn products_keep_the_units_of_the_left_operand() {
+        let mm = unit("mm");
+        assert_eq!(mm.mul(&mm.aligned(&unit("cm"))).to_string(), "mm^2");
+        assert_eq!(unit("mm/s").mul(&unit("s")).to_string(), "mm");
+        assert!(unit("mm").div(&unit("mm")).is_none());
+        assert_eq!(unit("s").mul(&unit("mm")).to_string(), "mm*s");
+        assert_eq!(unit("mm/s").aligned(&unit("m/ms")).to_string(), "mm/s");
+    }
+}